@@ -1,114 +1,697 @@
+use std::borrow::Cow;
 use std::fs::File;
 use std::io::{self, BufRead, BufReader};
 use std::path::Path;
+use std::sync::Arc;
 
 use anyhow::{Context, Result};
 
-use crate::languages::{find_language_index_for_path, language_markers_bytes};
-use crate::types::FileCounts;
-use once_cell::sync::OnceCell;
+use indexmap::IndexMap;
 
-struct AnalyzerConfig {
-    no_mmap: bool,
-    mmap_threshold: u64,
+use crate::languages::{
+    DETECTION_SAMPLE_LEN, EmbedSyntax, LanguageRegistry, LanguageSyntax, QuotePair,
+};
+use crate::types::{FileAnalysis, FileCounts, GeneratedKind};
+use once_cell::sync::{Lazy, OnceCell};
+
+const DEFAULT_MMAP_THRESHOLD: u64 = 4 * 1024 * 1024;
+
+/// Version of the counting rules, part of every cache key. Bump it whenever a change to
+/// the analyzer (not to the language definitions, which are hashed) changes any count.
+pub const ANALYZER_VERSION: u32 = 2;
+
+/// Settings for an [`Analyzer`].
+#[derive(Debug, Clone)]
+pub struct AnalyzerOptions {
+    /// Never memory-map files
+    pub no_mmap: bool,
+    /// Memory-map files at least this large (bytes)
+    pub mmap_threshold: u64,
+    /// Count docstrings as comments instead of code
+    pub docstrings_as_comments: bool,
 }
 
-static ANALYZER_CONFIG: OnceCell<AnalyzerConfig> = OnceCell::new();
+impl Default for AnalyzerOptions {
+    fn default() -> Self {
+        Self {
+            no_mmap: false,
+            mmap_threshold: DEFAULT_MMAP_THRESHOLD,
+            docstrings_as_comments: false,
+        }
+    }
+}
 
-pub fn set_analyzer_config(no_mmap: bool, mmap_threshold: Option<u64>) {
-    let _ = ANALYZER_CONFIG.set(AnalyzerConfig {
-        no_mmap,
-        mmap_threshold: mmap_threshold.unwrap_or(4 * 1024 * 1024),
-    });
+/// Counts lines of files with a fixed set of options and languages. Cheap to clone and
+/// safe to share across threads; differently configured analyzers can run side by side.
+#[derive(Debug, Clone)]
+pub struct Analyzer {
+    options: AnalyzerOptions,
+    registry: Arc<LanguageRegistry>,
 }
 
-pub fn analyze_file(path: &Path) -> Result<FileCounts> {
-    let file = File::open(path).with_context(|| format!("open file: {}", path.display()))?;
-    // Use mmap for large files to reduce syscall overhead (configurable)
-    if let Some(cfg) = ANALYZER_CONFIG.get() {
-        if !cfg.no_mmap {
+impl Default for Analyzer {
+    fn default() -> Self {
+        Self::builder().build()
+    }
+}
+
+/// Builder for [`Analyzer`]; starts from [`AnalyzerOptions::default`] and the built-in languages.
+#[derive(Debug, Clone, Default)]
+pub struct AnalyzerBuilder {
+    options: AnalyzerOptions,
+    registry: Option<Arc<LanguageRegistry>>,
+}
+
+impl AnalyzerBuilder {
+    pub fn options(mut self, options: AnalyzerOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    pub fn no_mmap(mut self, no_mmap: bool) -> Self {
+        self.options.no_mmap = no_mmap;
+        self
+    }
+
+    pub fn mmap_threshold(mut self, bytes: u64) -> Self {
+        self.options.mmap_threshold = bytes;
+        self
+    }
+
+    pub fn docstrings_as_comments(mut self, yes: bool) -> Self {
+        self.options.docstrings_as_comments = yes;
+        self
+    }
+
+    pub fn registry(mut self, registry: Arc<LanguageRegistry>) -> Self {
+        self.registry = Some(registry);
+        self
+    }
+
+    pub fn build(self) -> Analyzer {
+        Analyzer {
+            options: self.options,
+            registry: self.registry.unwrap_or_else(LanguageRegistry::builtin),
+        }
+    }
+}
+
+impl Analyzer {
+    pub fn builder() -> AnalyzerBuilder {
+        AnalyzerBuilder::default()
+    }
+
+    pub fn options(&self) -> &AnalyzerOptions {
+        &self.options
+    }
+
+    pub fn registry(&self) -> &Arc<LanguageRegistry> {
+        &self.registry
+    }
+
+    pub fn analyze_file(&self, path: &Path) -> Result<FileCounts> {
+        self.analyze_file_regions(path).map(|a| a.combined())
+    }
+
+    /// Like `analyze_file`, but keeps lines of embedded languages separate.
+    pub fn analyze_file_regions(&self, path: &Path) -> Result<FileAnalysis> {
+        self.analyze_file_with(path, None)
+    }
+
+    /// Like `analyze_file_regions`, but counts the file as registry language `language`
+    /// instead of detecting it (e.g. a `linguist-language` override).
+    pub fn analyze_file_regions_as(&self, path: &Path, language: usize) -> Result<FileAnalysis> {
+        self.analyze_file_with(path, Some(language))
+    }
+
+    fn analyze_file_with(&self, path: &Path, language: Option<usize>) -> Result<FileAnalysis> {
+        // Detect from the file itself, which also sees modelines at its end
+        let language = language.or_else(|| self.registry.find_index_for_path(path));
+        let file = File::open(path).with_context(|| format!("open file: {}", path.display()))?;
+        // Use mmap for large files to reduce syscall overhead (configurable)
+        if !self.options.no_mmap {
             if let Ok(meta) = file.metadata() {
-                if meta.len() >= cfg.mmap_threshold {
+                if meta.len() >= self.options.mmap_threshold {
                     // Safety: file is not mutated while mapping; read-only map
                     if let Ok(mmap) = unsafe { memmap2::Mmap::map(&file) } {
-                        let mut rdr = std::io::Cursor::new(&mmap[..]);
-                        return analyze_reader(&mut rdr, path);
+                        return self.count_regions(&mut &mmap[..], path, language);
+                    }
+                }
+            }
+        }
+        let mut reader = BufReader::new(file);
+        self.count_regions(&mut reader, path, language)
+    }
+
+    /// Analyze in-memory content (e.g. a git blob); `path_hint` selects the language.
+    pub fn analyze_bytes(&self, bytes: &[u8], path_hint: &Path) -> Result<FileCounts> {
+        self.analyze_bytes_regions(bytes, path_hint)
+            .map(|a| a.combined())
+    }
+
+    pub fn analyze_bytes_regions(&self, bytes: &[u8], path_hint: &Path) -> Result<FileAnalysis> {
+        let mut rdr = io::Cursor::new(bytes);
+        self.analyze_reader_regions(&mut rdr, path_hint)
+    }
+
+    pub fn analyze_reader<R: BufRead + ?Sized>(
+        &self,
+        reader: &mut R,
+        path_hint: &Path,
+    ) -> Result<FileCounts> {
+        self.analyze_reader_regions(reader, path_hint)
+            .map(|a| a.combined())
+    }
+
+    /// Region-aware analysis: for languages that embed others (HTML `<script>`/`<style>`,
+    /// Markdown code fences), lines inside each region are counted under the embedded
+    /// language instead of the file's own.
+    pub fn analyze_reader_regions<R: BufRead + ?Sized>(
+        &self,
+        reader: &mut R,
+        path_hint: &Path,
+    ) -> Result<FileAnalysis> {
+        self.count_regions(reader, path_hint, None)
+    }
+
+    /// Like `analyze_reader_regions`, but counts the content as registry language `language`.
+    pub fn analyze_reader_regions_as<R: BufRead + ?Sized>(
+        &self,
+        reader: &mut R,
+        path_hint: &Path,
+        language: usize,
+    ) -> Result<FileAnalysis> {
+        self.count_regions(reader, path_hint, Some(language))
+    }
+
+    fn count_regions<R: BufRead + ?Sized>(
+        &self,
+        reader: &mut R,
+        path_hint: &Path,
+        language: Option<usize>,
+    ) -> Result<FileAnalysis> {
+        let mut analysis = FileAnalysis {
+            counts: FileCounts::one_file(),
+            embedded: IndexMap::new(),
+            generated: is_lockfile(path_hint).then_some(GeneratedKind::Lockfile),
+            binary: false,
+        };
+        let verdict = self.classify(reader, path_hint, language, |_, owner, kind| {
+            let counts = match owner {
+                None => &mut analysis.counts,
+                Some(idx) => {
+                    let name = &self.registry[idx].name;
+                    let slot = match analysis.embedded.get_index_of(name) {
+                        Some(slot) => slot,
+                        None => {
+                            analysis
+                                .embedded
+                                .insert_full(name.clone(), FileCounts::default())
+                                .0
+                        }
+                    };
+                    &mut analysis.embedded[slot]
+                }
+            };
+            count_line(counts, kind);
+        })?;
+        if verdict.binary {
+            return Ok(FileAnalysis {
+                binary: true,
+                ..FileAnalysis::default()
+            });
+        }
+        analysis.generated = analysis.generated.or(verdict.generated);
+        Ok(analysis)
+    }
+
+    /// Classify every line of `reader` exactly as the line counts do, calling `visit` with
+    /// the 1-based line number and its kind, in order. Lines inside embedded regions
+    /// (e.g. `<script>` in HTML) are classified with the embedded language's syntax.
+    pub fn classify_lines<R: BufRead + ?Sized>(
+        &self,
+        reader: &mut R,
+        path_hint: &Path,
+        mut visit: impl FnMut(usize, LineKind),
+    ) -> Result<()> {
+        self.classify(reader, path_hint, None, |line_no, _, kind| {
+            visit(line_no, kind)
+        })?;
+        Ok(())
+    }
+
+    /// Core line classifier; `visit` also receives the embedded language owning the line
+    /// (`None` for the file's own language). `language` skips detection from `path_hint`.
+    /// Returns whether the content looks binary (no lines are visited) or generated.
+    fn classify<R: BufRead + ?Sized>(
+        &self,
+        reader: &mut R,
+        path_hint: &Path,
+        language: Option<usize>,
+        mut visit: impl FnMut(usize, Option<usize>, LineKind),
+    ) -> Result<ContentVerdict> {
+        // UTF-16 (with a BOM) is transcoded to UTF-8 first; a UTF-8 BOM is dropped so it
+        // does not hide a comment marker on the first line
+        let head = reader.fill_buf().context("read content")?;
+        match sniff_encoding(head) {
+            Encoding::Utf16 { big_endian } => {
+                let text = transcode_utf16(reader, big_endian)
+                    .with_context(|| format!("read: {}", path_hint.display()))?;
+                return self.classify(&mut text.as_slice(), path_hint, language, visit);
+            }
+            Encoding::Binary => {
+                return Ok(ContentVerdict {
+                    binary: true,
+                    generated: None,
+                });
+            }
+            Encoding::Utf8 { bom } => {
+                if bom {
+                    reader.consume(UTF8_BOM.len());
+                }
+            }
+        }
+
+        let registry = &*self.registry;
+        let docstrings_as_comments = self.options.docstrings_as_comments;
+        // Locate language by name, with heuristics and shebangs looking at the buffered start
+        // of the content; unknown languages have no markers: every non-blank line is code
+        let lang_idx = match language {
+            Some(idx) => Some(idx),
+            None => {
+                let sample = reader.fill_buf().context("read content")?;
+                registry.detect(path_hint, Some(sample)).map(|d| d.index)
+            }
+        };
+        let syntax: &LanguageSyntax = match lang_idx {
+            Some(idx) => registry.syntax(idx),
+            None => &PLAIN_SYNTAX,
+        };
+        let mut scanner = LineScanner::new(syntax, docstrings_as_comments);
+        let mut regions = lang_idx
+            .and_then(|idx| registry[idx].embedded)
+            .map(|kind| RegionSplitter::new(kind, registry));
+        // Scanner for the current embedded region; regions are always delimited by parent lines
+        let mut child: Option<LineScanner> = None;
+        // Comment lines held back until the next line shows whether they document it
+        // (`doc_keywords`, e.g. Go package comments); all share one owner
+        let mut held: Vec<(usize, Option<usize>)> = Vec::new();
+        let mut line_no = 0;
+        let mut generated = GeneratedCheck::new();
+
+        for_each_line(reader, |line| {
+            line_no += 1;
+            let owner = match regions.as_mut() {
+                Some(r) => r.route(line, scanner.at_comment(line)),
+                None => None,
+            };
+            let sub = match owner {
+                None => {
+                    child = None;
+                    &mut scanner
+                }
+                Some(idx) => child.get_or_insert_with(|| {
+                    LineScanner::new(registry.syntax(idx), docstrings_as_comments)
+                }),
+            };
+            let kind = sub.classify(line);
+            generated.observe(line_no, line, kind);
+            if kind == LineKind::Comment && !sub.syntax.doc_keywords.is_empty() {
+                if held.first().is_some_and(|&(_, o)| o != owner) {
+                    for (n, o) in held.drain(..) {
+                        visit(n, o, LineKind::Comment);
                     }
                 }
+                held.push((line_no, owner));
+                return;
+            }
+            let promote = sub.documents_above && held.first().is_some_and(|&(_, o)| o == owner);
+            let held_kind = if promote {
+                LineKind::Doc
+            } else {
+                LineKind::Comment
+            };
+            for (n, o) in held.drain(..) {
+                visit(n, o, held_kind);
             }
+            visit(line_no, owner, kind);
+        })
+        .with_context(|| format!("read: {}", path_hint.display()))?;
+
+        for (n, o) in held {
+            visit(n, o, LineKind::Comment);
+        }
+        Ok(ContentVerdict {
+            binary: false,
+            generated: generated.verdict(),
+        })
+    }
+}
+
+/// What `Analyzer::classify` concluded about the content as a whole.
+struct ContentVerdict {
+    binary: bool,
+    generated: Option<GeneratedKind>,
+}
+
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// Text encoding sniffed from the start of the content.
+#[derive(Debug, PartialEq, Eq)]
+enum Encoding {
+    Utf8 {
+        bom: bool,
+    },
+    Utf16 {
+        big_endian: bool,
+    },
+    /// A NUL byte within the detection sample: not text
+    Binary,
+}
+
+fn sniff_encoding(head: &[u8]) -> Encoding {
+    match head {
+        [0xFF, 0xFE, ..] => Encoding::Utf16 { big_endian: false },
+        [0xFE, 0xFF, ..] => Encoding::Utf16 { big_endian: true },
+        _ if memchr::memchr(0, &head[..head.len().min(DETECTION_SAMPLE_LEN)]).is_some() => {
+            Encoding::Binary
         }
+        _ => Encoding::Utf8 {
+            bom: head.starts_with(UTF8_BOM),
+        },
     }
-    let mut reader = BufReader::new(file);
-    analyze_reader(&mut reader, path)
 }
 
+/// `bytes` as the line classifier sees them: UTF-16 transcoded to UTF-8 and a UTF-8 BOM
+/// dropped. `None` for binary content, which is not classified.
+pub fn decode_text(bytes: &[u8]) -> Option<Cow<'_, [u8]>> {
+    match sniff_encoding(bytes) {
+        Encoding::Utf16 { big_endian } => Some(Cow::Owned(
+            transcode_utf16(&mut &bytes[..], big_endian).unwrap_or_default(),
+        )),
+        Encoding::Binary => None,
+        Encoding::Utf8 { bom } => Some(Cow::Borrowed(if bom {
+            &bytes[UTF8_BOM.len()..]
+        } else {
+            bytes
+        })),
+    }
+}
+
+/// Read the rest of a UTF-16 stream, BOM included, and re-encode it as UTF-8. Unpaired
+/// surrogates become U+FFFD; a trailing odd byte is dropped.
+fn transcode_utf16<R: BufRead + ?Sized>(reader: &mut R, big_endian: bool) -> io::Result<Vec<u8>> {
+    let mut raw = Vec::new();
+    io::Read::read_to_end(reader, &mut raw)?;
+    let units = raw
+        .get(2..)
+        .unwrap_or_default()
+        .chunks_exact(2)
+        .map(|pair| {
+            let pair = [pair[0], pair[1]];
+            if big_endian {
+                u16::from_be_bytes(pair)
+            } else {
+                u16::from_le_bytes(pair)
+            }
+        });
+    let text: String = char::decode_utf16(units)
+        .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+        .collect();
+    Ok(text.into_bytes())
+}
+
+// Lines at the top of a file searched for generated-code markers
+const GENERATED_MARKER_LINES: usize = 5;
+
+// Lowercase starts of comments marking generated code, after the comment markers
+const GENERATED_PREFIXES: &[&str] = &[
+    "@generated",
+    "<auto-generated",
+    "autogenerated by",
+    "auto-generated by",
+    "automatically generated by",
+    "this file is autogenerated",
+    "this file is auto-generated",
+    "this file is automatically generated",
+    "this file was automatically generated",
+    "this file is generated",
+    "this file was generated",
+];
+
+/// Whether a comment line marks its file as generated: Go's `Code generated ... DO NOT
+/// EDIT.`, `@generated`, `<auto-generated>` and `Autogenerated by ...` forms, matched at
+/// the start of the comment text so that comments merely mentioning them do not count.
+fn is_generated_marker(line: &[u8]) -> bool {
+    let lower = String::from_utf8_lossy(line).to_ascii_lowercase();
+    let mut text = lower.trim_start();
+    loop {
+        if let Some(rest) = text.strip_prefix("<!--") {
+            text = rest;
+        } else {
+            match text.chars().next() {
+                Some(c) if c.is_whitespace() || "/*#;-!%'\"({".contains(c) => {
+                    text = &text[c.len_utf8()..];
+                }
+                _ => break,
+            }
+        }
+    }
+    let do_not_edit = text.contains("do not edit");
+    ((text.starts_with("code generated ") || text.starts_with("generated ")) && do_not_edit)
+        || (text.starts_with("do not edit") && text.contains("generated"))
+        || text.contains("file is @generated")
+        || GENERATED_PREFIXES.iter().any(|p| text.starts_with(p))
+}
+
+// Lines at least this long count towards the minified heuristic
+const MINIFIED_LINE_LEN: usize = 1000;
+
+// Bytes of long lines sampled for the whitespace share and entropy
+const MINIFIED_SAMPLE_LEN: usize = 64 * 1024;
+
+const LOCKFILES: &[&str] = &[
+    "package-lock.json",
+    "npm-shrinkwrap.json",
+    "yarn.lock",
+    "pnpm-lock.yaml",
+    "bun.lock",
+    "cargo.lock",
+    "gemfile.lock",
+    "pipfile.lock",
+    "poetry.lock",
+    "uv.lock",
+    "composer.lock",
+    "go.sum",
+    "podfile.lock",
+    "flake.lock",
+    "mix.lock",
+    "pubspec.lock",
+    "packages.lock.json",
+    "gradle.lockfile",
+    "package.resolved",
+];
+
+/// Whether `path` names a package manager lockfile (counted as generated).
+pub(crate) fn is_lockfile(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|n| n.to_str())
+        .is_some_and(|n| LOCKFILES.contains(&n.to_ascii_lowercase().as_str()))
+}
+
+/// Watches lines as they are classified for signs of generated content: a marker comment
+/// (see `is_generated_marker`) near the top, or bytes dominated by very long lines that are dense (little whitespace)
+/// yet varied (byte entropy of at least 4 bits), as in minified bundles.
+struct GeneratedCheck {
+    marker: bool,
+    total_bytes: usize,
+    long_bytes: usize,
+    // Byte histogram of the first `MINIFIED_SAMPLE_LEN` bytes of long lines
+    histogram: [u32; 256],
+    sampled: usize,
+}
+
+impl GeneratedCheck {
+    fn new() -> Self {
+        Self {
+            marker: false,
+            total_bytes: 0,
+            long_bytes: 0,
+            histogram: [0; 256],
+            sampled: 0,
+        }
+    }
+
+    fn observe(&mut self, line_no: usize, line: &[u8], kind: LineKind) {
+        if line_no <= GENERATED_MARKER_LINES
+            && !self.marker
+            && matches!(kind, LineKind::Comment | LineKind::Doc)
+        {
+            self.marker = is_generated_marker(line);
+        }
+        self.total_bytes += line.len() + 1;
+        if line.len() >= MINIFIED_LINE_LEN {
+            self.long_bytes += line.len() + 1;
+            let take = line.len().min(MINIFIED_SAMPLE_LEN - self.sampled);
+            for &b in &line[..take] {
+                self.histogram[b as usize] += 1;
+            }
+            self.sampled += take;
+        }
+    }
+
+    fn verdict(&self) -> Option<GeneratedKind> {
+        if self.marker {
+            return Some(GeneratedKind::Marker);
+        }
+        if self.sampled == 0 || self.long_bytes * 2 < self.total_bytes {
+            return None;
+        }
+        let n = self.sampled as f64;
+        let whitespace: u32 = [b' ', b'\t']
+            .iter()
+            .map(|&b| self.histogram[b as usize])
+            .sum();
+        let entropy: f64 = self
+            .histogram
+            .iter()
+            .filter(|&&c| c > 0)
+            .map(|&c| {
+                let p = c as f64 / n;
+                -p * p.log2()
+            })
+            .sum();
+        (f64::from(whitespace) / n < 0.10 && entropy >= 4.0).then_some(GeneratedKind::Minified)
+    }
+}
+
+/// How a single line is counted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LineKind {
+    Blank,
+    Code,
+    Comment,
+    Doc,
+}
+
+impl LineKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LineKind::Blank => "blank",
+            LineKind::Code => "code",
+            LineKind::Comment => "comment",
+            LineKind::Doc => "doc",
+        }
+    }
+}
+
+impl std::fmt::Display for LineKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.pad(self.as_str())
+    }
+}
+
+fn count_line(counts: &mut FileCounts, kind: LineKind) {
+    counts.total += 1;
+    match kind {
+        LineKind::Blank => counts.blank += 1,
+        LineKind::Code => counts.code += 1,
+        LineKind::Comment => counts.comment += 1,
+        LineKind::Doc => counts.doc += 1,
+    }
+}
+
+// Deprecated process-wide configuration, kept for the free-function API below
+static LEGACY_ANALYZER: OnceCell<Analyzer> = OnceCell::new();
+
+fn legacy_analyzer() -> &'static Analyzer {
+    LEGACY_ANALYZER.get_or_init(Analyzer::default)
+}
+
+#[deprecated(note = "build an `Analyzer` with `Analyzer::builder()` instead")]
+pub fn set_analyzer_config(
+    no_mmap: bool,
+    mmap_threshold: Option<u64>,
+    docstrings_as_comments: bool,
+) {
+    let _ = LEGACY_ANALYZER.set(
+        Analyzer::builder()
+            .no_mmap(no_mmap)
+            .mmap_threshold(mmap_threshold.unwrap_or(DEFAULT_MMAP_THRESHOLD))
+            .docstrings_as_comments(docstrings_as_comments)
+            .build(),
+    );
+}
+
+#[deprecated(note = "use `Analyzer::analyze_file`")]
+pub fn analyze_file(path: &Path) -> Result<FileCounts> {
+    legacy_analyzer().analyze_file(path)
+}
+
+#[deprecated(note = "use `Analyzer::analyze_file_regions`")]
+pub fn analyze_file_regions(path: &Path) -> Result<FileAnalysis> {
+    legacy_analyzer().analyze_file_regions(path)
+}
+
+#[deprecated(note = "use `Analyzer::analyze_reader`")]
 pub fn analyze_reader<R: BufRead + ?Sized>(reader: &mut R, path_hint: &Path) -> Result<FileCounts> {
-    // Locate language by extension; unknown -> skip counts but still produce 0s
-    let lang_idx = find_language_index_for_path(path_hint);
-
-    let mut counts = FileCounts::one_file();
-    let mut buf = Vec::with_capacity(8192);
-    let mut in_block: Option<(Vec<u8>, Vec<u8>)> = None;
-
-    // Obtain markers
-    type MarkersTuple = (&'static [Vec<u8>], Option<(&'static [u8], &'static [u8])>);
-    let (line_markers_vec, block_markers_bytes): MarkersTuple = if let Some(idx) = lang_idx {
-        language_markers_bytes(idx)
-    } else {
-        (&[], None)
-    };
+    legacy_analyzer().analyze_reader(reader, path_hint)
+}
+
+#[deprecated(note = "use `Analyzer::analyze_reader_regions`")]
+pub fn analyze_reader_regions<R: BufRead + ?Sized>(
+    reader: &mut R,
+    path_hint: &Path,
+) -> Result<FileAnalysis> {
+    legacy_analyzer().analyze_reader_regions(reader, path_hint)
+}
+
+// Backward-compatible wrapper for callers that pass an owned reader
+#[deprecated(note = "use `Analyzer::analyze_reader`")]
+pub fn analyze_reader_owned<R: BufRead>(mut reader: R, path_hint: &Path) -> Result<FileCounts> {
+    legacy_analyzer().analyze_reader(&mut reader, path_hint)
+}
 
+#[deprecated(note = "use `Analyzer::classify_lines`")]
+pub fn classify_lines<R: BufRead + ?Sized>(
+    reader: &mut R,
+    path_hint: &Path,
+    visit: impl FnMut(usize, LineKind),
+) -> Result<()> {
+    legacy_analyzer().classify_lines(reader, path_hint, visit)
+}
+
+/// Feed each line (without its `\n` / `\r\n`) to `f`, splitting chunks with memchr for speed.
+fn for_each_line<R: BufRead + ?Sized>(reader: &mut R, mut f: impl FnMut(&[u8])) -> io::Result<()> {
     // Fast zero-byte file handling if possible
     if let Ok(slice) = reader.fill_buf() {
         if slice.is_empty() {
-            return Ok(counts);
+            return Ok(());
         }
     }
 
-    // Process by chunks and split on newlines using memchr for speed
+    let mut buf = vec![0u8; 8192];
     let mut pending: Vec<u8> = Vec::new();
     loop {
-        buf.resize(8192, 0);
         let n = match io::Read::read(reader, &mut buf) {
-            Ok(0) => 0,
             Ok(n) => n,
             Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
-            Err(e) => return Err(e).with_context(|| format!("read: {}", path_hint.display())),
+            Err(e) => return Err(e),
         };
         if n == 0 {
             if !pending.is_empty() {
-                process_line(
-                    &mut counts,
-                    line_markers_vec,
-                    &block_markers_bytes,
-                    &mut in_block,
-                    trim_cr(&pending),
-                );
-                pending.clear();
+                f(trim_cr(&pending));
             }
-            break;
+            return Ok(());
         }
         let chunk = &buf[..n];
         let mut start = 0;
         for i in memchr::memchr_iter(b'\n', chunk) {
             if pending.is_empty() {
-                process_line(
-                    &mut counts,
-                    line_markers_vec,
-                    &block_markers_bytes,
-                    &mut in_block,
-                    trim_cr(&chunk[start..i]),
-                );
+                f(trim_cr(&chunk[start..i]));
             } else {
                 pending.extend_from_slice(&chunk[start..i]);
-                let line = trim_cr(&pending);
-                process_line(
-                    &mut counts,
-                    line_markers_vec,
-                    &block_markers_bytes,
-                    &mut in_block,
-                    line,
-                );
+                f(trim_cr(&pending));
                 pending.clear();
             }
             start = i + 1;
@@ -117,13 +700,6 @@ pub fn analyze_reader<R: BufRead + ?Sized>(reader: &mut R, path_hint: &Path) ->
             pending.extend_from_slice(&chunk[start..]);
         }
     }
-
-    Ok(counts)
-}
-
-// Backward-compatible wrapper for callers that pass an owned reader
-pub fn analyze_reader_owned<R: BufRead>(mut reader: R, path_hint: &Path) -> Result<FileCounts> {
-    analyze_reader(&mut reader, path_hint)
 }
 
 fn trim_ascii_start(mut s: &[u8]) -> &[u8] {
@@ -153,72 +729,620 @@ fn find_bytes(hay: &[u8], needle: &[u8]) -> Option<usize> {
     memchr::memmem::find(hay, needle)
 }
 
-fn process_line(
-    counts: &mut FileCounts,
-    line_markers: &[Vec<u8>],
-    block_markers: &Option<(&[u8], &[u8])>,
-    in_block: &mut Option<(Vec<u8>, Vec<u8>)>,
-    raw: &[u8],
-) {
-    counts.total += 1;
-    let trimmed = trim_ascii_start(raw);
-    if trimmed.is_empty() {
-        counts.blank += 1;
-        return;
-    }
-
-    // If already in a block, search for end
-    if let &mut Some((_, ref end)) = in_block {
-        if let Some(idx) = find_bytes(trimmed, end.as_slice()) {
-            let after = &trimmed[idx + end.len()..];
-            *in_block = None;
-            if trim_ascii_start(after).is_empty() {
-                counts.comment += 1;
-            } else {
-                counts.code += 1;
+/// Scanner state carried from one line to the next.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ScanState {
+    Code,
+    /// Inside a block comment opened by `LanguageSyntax::block_markers[pair]` (or
+    /// `doc_block_markers[pair]` for doc comments), at the given nesting depth
+    /// (always 1 unless the language nests)
+    Block {
+        pair: usize,
+        depth: usize,
+        doc: bool,
+    },
+    /// Inside a string literal; index into `LanguageSyntax::quotes`. Docstrings count as comment.
+    Str {
+        quote: usize,
+        doc: bool,
+    },
+}
+
+static PLAIN_SYNTAX: Lazy<LanguageSyntax> = Lazy::new(LanguageSyntax::plain);
+
+/// Length of the string body up to and including the closing delimiter, if it closes on this line.
+fn string_end(s: &[u8], quote: &QuotePair) -> Option<usize> {
+    let mut i = 0;
+    while i < s.len() {
+        if quote.escapes && s[i] == b'\\' {
+            i += 2;
+            continue;
+        }
+        if s[i..].starts_with(&quote.end) {
+            return Some(i + quote.end.len());
+        }
+        i += 1;
+    }
+    None
+}
+
+/// Length of a char literal such as `'a'`, `'"'` or `'\''` at the start of `s`.
+/// Returns `None` for lifetimes and labels (`'a`, `'outer:`).
+fn char_literal_len(s: &[u8]) -> Option<usize> {
+    match s.get(1)? {
+        b'\\' => {
+            // Escapes: '\n', '\'', '\x7f', '\u{1F600}'
+            let close = s.iter().skip(3).take(10).position(|&b| b == b'\'')?;
+            Some(3 + close + 1)
+        }
+        &lead => {
+            let width = match lead {
+                0x00..=0x7f => 1,
+                0xc0..=0xdf => 2,
+                0xe0..=0xef => 3,
+                _ => 4,
+            };
+            (s.get(1 + width) == Some(&b'\'')).then_some(width + 2)
+        }
+    }
+}
+
+/// Whether a code line ending in `b` leaves an expression open, so that a string starting
+/// the next line is a continuation rather than a statement (and thus not a docstring).
+fn continues_expression(b: u8) -> bool {
+    matches!(b, b'(' | b'[' | b'{' | b',' | b'=' | b'+' | b'\\')
+}
+
+/// Length of `marker` if `rest` starts with it. A marker starting with `^` only matches in
+/// column 1, that is when `rest` is the whole line and the line is not indented.
+fn match_marker(rest: &[u8], marker: &[u8], column_one: bool) -> Option<usize> {
+    match marker.strip_prefix(b"^") {
+        Some(anchored) => (column_one && rest.starts_with(anchored)).then_some(anchored.len()),
+        None => rest.starts_with(marker).then_some(marker.len()),
+    }
+}
+
+/// Position and length of the first `marker` in `rest` (see `match_marker`).
+fn find_marker(rest: &[u8], marker: &[u8], column_one: bool) -> Option<(usize, usize)> {
+    match marker.strip_prefix(b"^") {
+        Some(_) => match_marker(rest, marker, column_one).map(|len| (0, len)),
+        None => find_bytes(rest, marker).map(|pos| (pos, marker.len())),
+    }
+}
+
+/// A doc opener directly followed by its own last byte (`////`, `/***`) is an ordinary comment.
+fn is_doc_opener(rest: &[u8], marker: &[u8], column_one: bool) -> Option<usize> {
+    match_marker(rest, marker, column_one).filter(|&len| rest.get(len) != marker.last())
+}
+
+/// Here-document opener at the start of `rest`, e.g. `<<EOF` or `<<-'EOF'`: its length and
+/// terminating word.
+fn heredoc_start<'l>(rest: &'l [u8], openers: &[Vec<u8>]) -> Option<(usize, &'l [u8])> {
+    let opener = openers.iter().find(|m| rest.starts_with(m))?;
+    let after = &rest[opener.len()..];
+    let quote = after.first().filter(|&&b| b == b'\'' || b == b'"').copied();
+    let word_start = usize::from(quote.is_some());
+    let word_len = after[word_start..]
+        .iter()
+        .take_while(|&&b| b.is_ascii_alphanumeric() || b == b'_')
+        .count();
+    let word = &after[word_start..word_start + word_len];
+    if word.is_empty() || word[0].is_ascii_digit() {
+        return None;
+    }
+    let mut len = opener.len() + word_start + word_len;
+    if let Some(q) = quote {
+        if after.get(len - opener.len()) != Some(&q) {
+            return None;
+        }
+        len += 1;
+    }
+    Some((len, word))
+}
+
+/// Whether `line` (without indentation) ends the here-document terminated by `word`.
+fn ends_heredoc(line: &[u8], word: &[u8]) -> bool {
+    line.starts_with(word)
+        && line
+            .get(word.len())
+            .is_none_or(|&b| !(b.is_ascii_alphanumeric() || b == b'_'))
+}
+
+/// Classifies lines one at a time, carrying comment and string state across lines.
+struct LineScanner<'a> {
+    syntax: &'a LanguageSyntax,
+    docstrings_as_comments: bool,
+    state: ScanState,
+    // Last significant byte of the most recent code line
+    last_code_byte: Option<u8>,
+    // Whether the last classified line is documented by the comments directly above it
+    documents_above: bool,
+    // Terminating word of the here-document the scanner is in
+    heredoc: Option<Vec<u8>>,
+}
+
+impl<'a> LineScanner<'a> {
+    fn new(syntax: &'a LanguageSyntax, docstrings_as_comments: bool) -> Self {
+        Self {
+            syntax,
+            docstrings_as_comments,
+            state: ScanState::Code,
+            last_code_byte: None,
+            documents_above: false,
+            heredoc: None,
+        }
+    }
+
+    /// Docstring opener at the start of `line`, if docstrings count as comments here.
+    /// A docstring must begin a statement-like line: the previous code line may not
+    /// leave an expression open.
+    fn docstring_start(&self, line: &[u8]) -> Option<(usize, usize)> {
+        if !self.docstrings_as_comments || self.state != ScanState::Code {
+            return None;
+        }
+        if self.last_code_byte.is_some_and(continues_expression) {
+            return None;
+        }
+        self.syntax
+            .doc_strings
+            .iter()
+            .find(|(marker, _)| line.starts_with(marker))
+            .map(|(marker, quote)| (marker.len(), *quote))
+    }
+
+    /// Block comment opening at the start of `rest` (doc pairs first), with the opener
+    /// length; `column_one` tells whether `rest` starts in column 1.
+    fn block_start(&self, rest: &[u8], column_one: bool) -> Option<(ScanState, usize)> {
+        let syntax = self.syntax;
+        // `/**/` is an empty ordinary comment, not an unterminated doc comment
+        let empty_block = syntax.block_markers.iter().any(|(s, e)| {
+            match_marker(rest, s, column_one)
+                .is_some_and(|len| match_marker(&rest[len..], e, false).is_some())
+        });
+        if !empty_block
+            && let Some((pair, len)) =
+                syntax
+                    .doc_block_markers
+                    .iter()
+                    .enumerate()
+                    .find_map(|(pair, (start, _))| {
+                        Some((pair, is_doc_opener(rest, start, column_one)?))
+                    })
+        {
+            let state = ScanState::Block {
+                pair,
+                depth: 1,
+                doc: true,
+            };
+            return Some((state, len));
+        }
+        // Pairs are ordered longest first, so the first hit is the best match here
+        syntax
+            .block_markers
+            .iter()
+            .enumerate()
+            .find_map(|(pair, (start, _))| Some((pair, match_marker(rest, start, column_one)?)))
+            .map(|(pair, len)| {
+                let state = ScanState::Block {
+                    pair,
+                    depth: 1,
+                    doc: false,
+                };
+                (state, len)
+            })
+    }
+
+    /// Whether `raw` is inside, or opens with, a block comment; embedded-region tags
+    /// there are commented out.
+    fn at_comment(&self, raw: &[u8]) -> bool {
+        let line = trim_ascii_start(raw);
+        matches!(self.state, ScanState::Block { .. })
+            || self.block_start(line, line.len() == raw.len()).is_some()
+    }
+
+    fn classify(&mut self, raw: &[u8]) -> LineKind {
+        self.documents_above = false;
+        let line = trim_ascii_start(raw);
+        if line.is_empty() {
+            return LineKind::Blank;
+        }
+        if let Some(word) = &self.heredoc {
+            if ends_heredoc(line, word) {
+                self.heredoc = None;
+            }
+            return LineKind::Code;
+        }
+
+        let syntax = self.syntax;
+        // Markers starting with `^` need column 1: offset 0 of an unindented line
+        let indented = line.len() != raw.len();
+        let column_one = |i: usize| i == 0 && !indented;
+        let mut heredoc = None;
+        let mut has_code = false;
+        let mut has_doc = false;
+        let mut i = 0;
+        if let Some((len, quote)) = self.docstring_start(line) {
+            self.state = ScanState::Str { quote, doc: true };
+            i = len;
+        }
+        let starts_in_code = self.state == ScanState::Code;
+
+        // Walk the line once, tracking strings so that markers inside literals are ignored.
+        // Anything outside a comment makes the line code; otherwise doc comments win over
+        // ordinary ones.
+        while i < line.len() {
+            match self.state {
+                ScanState::Block { pair, depth, doc } => {
+                    has_doc |= doc;
+                    let (end, nest_start) = if doc {
+                        (
+                            &syntax.doc_block_markers[pair].1,
+                            &syntax.doc_block_nesting[pair],
+                        )
+                    } else {
+                        (&syntax.block_markers[pair].1, &syntax.block_nesting[pair])
+                    };
+                    let rest = &line[i..];
+                    let close = find_marker(rest, end, column_one(i));
+                    let open = nest_start
+                        .as_ref()
+                        .and_then(|s| find_marker(rest, s, column_one(i)));
+                    match (open, close) {
+                        (Some((o, len)), c) if c.is_none_or(|(c, _)| o < c) => {
+                            i += o + len;
+                            self.state = ScanState::Block {
+                                pair,
+                                depth: depth + 1,
+                                doc,
+                            };
+                        }
+                        (_, Some((c, len))) => {
+                            // A column-1 closer is a directive line (`=end pod`): what
+                            // follows it is still part of the comment
+                            i = if end.starts_with(b"^") {
+                                line.len()
+                            } else {
+                                i + c + len
+                            };
+                            self.state = if depth > 1 {
+                                ScanState::Block {
+                                    pair,
+                                    depth: depth - 1,
+                                    doc,
+                                }
+                            } else {
+                                ScanState::Code
+                            };
+                        }
+                        _ => break,
+                    }
+                }
+                ScanState::Str { quote, doc } => {
+                    has_code |= !doc;
+                    match string_end(&line[i..], &syntax.quotes[quote]) {
+                        Some(len) => {
+                            i += len;
+                            if !doc {
+                                self.last_code_byte = Some(line[i - 1]);
+                            }
+                            self.state = ScanState::Code;
+                        }
+                        None => break,
+                    }
+                }
+                ScanState::Code => {
+                    let b = line[i];
+                    if b.is_ascii_whitespace() {
+                        i += 1;
+                        continue;
+                    }
+                    if !syntax.is_lead_byte(b) {
+                        has_code = true;
+                        self.last_code_byte = Some(b);
+                        i += 1;
+                        continue;
+                    }
+                    let rest = &line[i..];
+                    if let Some((block, len)) = self.block_start(rest, column_one(i)) {
+                        // A doc comment opener alone on its line is documentation too
+                        has_doc |= matches!(block, ScanState::Block { doc: true, .. });
+                        self.state = block;
+                        i += len;
+                        continue;
+                    }
+                    if syntax
+                        .doc_line_markers
+                        .iter()
+                        .any(|m| is_doc_opener(rest, m, column_one(i)).is_some())
+                    {
+                        has_doc = true;
+                        break;
+                    }
+                    if syntax
+                        .line_markers
+                        .iter()
+                        .any(|m| match_marker(rest, m, column_one(i)).is_some())
+                    {
+                        break;
+                    }
+                    has_code = true;
+                    self.last_code_byte = Some(b);
+                    if heredoc.is_none()
+                        && let Some((len, word)) = heredoc_start(rest, &syntax.heredocs)
+                    {
+                        heredoc = Some(word.to_vec());
+                        i += len;
+                        continue;
+                    }
+                    if let Some(quote) = syntax
+                        .quotes
+                        .iter()
+                        .position(|q| rest.starts_with(&q.start))
+                    {
+                        self.state = ScanState::Str { quote, doc: false };
+                        i += syntax.quotes[quote].start.len();
+                        continue;
+                    }
+                    if syntax.char_literals && b == b'\'' {
+                        i += char_literal_len(rest).unwrap_or(1);
+                        continue;
+                    }
+                    i += 1;
+                }
             }
-            return;
+        }
+
+        // Only multiline strings (triple quotes, raw strings, ...) carry over to the next
+        // line, so a stray quote cannot turn the rest of the file into code
+        if let ScanState::Str { quote, .. } = self.state
+            && !syntax.quotes[quote].multiline
+        {
+            self.state = ScanState::Code;
+        }
+        // A here-document starts on the line after its opener
+        self.heredoc = heredoc;
+
+        if has_code {
+            // Comments directly above e.g. Go's `package` clause document it
+            self.documents_above = starts_in_code
+                && syntax
+                    .doc_keywords
+                    .iter()
+                    .any(|k| starts_with_word(line, k));
+            LineKind::Code
+        } else if has_doc {
+            LineKind::Doc
         } else {
-            counts.comment += 1;
-            return;
+            LineKind::Comment
         }
     }
+}
+
+/// Whether `line` starts with `word` followed by whitespace or the end of the line.
+fn starts_with_word(line: &[u8], word: &[u8]) -> bool {
+    line.starts_with(word) && line.get(word.len()).is_none_or(|b| b.is_ascii_whitespace())
+}
 
-    if let Some((start, end)) = block_markers {
-        if let Some(start_idx) = find_bytes(trimmed, start) {
-            if let Some(end_rel) = find_bytes(&trimmed[start_idx + start.len()..], end) {
-                let before = &trimmed[..start_idx];
-                let after = &trimmed[start_idx + start.len() + end_rel + end.len()..];
-                if trim_ascii_start(before).is_empty() && trim_ascii_start(after).is_empty() {
-                    counts.comment += 1;
-                } else {
-                    counts.code += 1;
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum HtmlTag {
+    Script,
+    Style,
+}
+
+impl HtmlTag {
+    fn close(self) -> &'static [u8] {
+        match self {
+            HtmlTag::Script => b"</script",
+            HtmlTag::Style => b"</style",
+        }
+    }
+}
+
+#[derive(Debug)]
+enum RegionState {
+    Parent,
+    /// Inside an opening tag whose `>` is on a later line; attributes collected so far
+    OpenTag {
+        tag: HtmlTag,
+        attrs: Vec<u8>,
+    },
+    /// Inside `<script>`/`<style>`; `None` keeps unknown content (e.g. templates) in the parent
+    Element {
+        tag: HtmlTag,
+        lang: Option<usize>,
+    },
+    /// Inside a fenced code block opened by `len` repetitions of `marker`
+    Fence {
+        marker: u8,
+        len: usize,
+        lang: Option<usize>,
+    },
+}
+
+/// Tracks embedded-language regions line by line. Opening and closing lines (tags,
+/// fences) belong to the parent language; lines in between to the embedded one.
+struct RegionSplitter<'a> {
+    kind: EmbedSyntax,
+    state: RegionState,
+    registry: &'a LanguageRegistry,
+}
+
+impl<'a> RegionSplitter<'a> {
+    fn new(kind: EmbedSyntax, registry: &'a LanguageRegistry) -> Self {
+        Self {
+            kind,
+            state: RegionState::Parent,
+            registry,
+        }
+    }
+
+    /// Embedded language owning `line`, or `None` for the parent language.
+    fn route(&mut self, line: &[u8], in_comment: bool) -> Option<usize> {
+        match std::mem::replace(&mut self.state, RegionState::Parent) {
+            RegionState::Parent => {
+                if !in_comment {
+                    self.state = match self.kind {
+                        EmbedSyntax::Html => open_html_element(line, self.registry),
+                        EmbedSyntax::Markdown => open_fence(line, self.registry),
+                    };
                 }
-                return;
-            } else {
-                // starts block; remains open
-                *in_block = Some((start.to_vec(), end.to_vec()));
-                let before = &trimmed[..start_idx];
-                if trim_ascii_start(before).is_empty() {
-                    counts.comment += 1;
-                } else {
-                    counts.code += 1;
+                None
+            }
+            RegionState::OpenTag { tag, mut attrs } => {
+                self.state = continue_open_tag(tag, &mut attrs, line, self.registry);
+                None
+            }
+            RegionState::Element { tag, lang } => {
+                if find_bytes(&line.to_ascii_lowercase(), tag.close()).is_some() {
+                    return None;
                 }
-                return;
+                self.state = RegionState::Element { tag, lang };
+                lang
+            }
+            RegionState::Fence { marker, len, lang } => {
+                let trimmed = trim_ascii_start(line);
+                let run = trimmed.iter().take_while(|&&b| b == marker).count();
+                if run >= len && trim_ascii_start(&trimmed[run..]).is_empty() {
+                    return None;
+                }
+                self.state = RegionState::Fence { marker, len, lang };
+                lang
             }
         }
     }
+}
+
+fn open_html_element(line: &[u8], registry: &LanguageRegistry) -> RegionState {
+    if !line.contains(&b'<') {
+        return RegionState::Parent;
+    }
+    let lower = line.to_ascii_lowercase();
+    for (tag, open) in [
+        (HtmlTag::Script, &b"<script"[..]),
+        (HtmlTag::Style, &b"<style"[..]),
+    ] {
+        let Some(at) = find_bytes(&lower, open) else {
+            continue;
+        };
+        let after = &lower[at + open.len()..];
+        // `<scripts>` or `<style-guide>` are other elements
+        if after
+            .first()
+            .is_some_and(|&b| b != b'>' && !b.is_ascii_whitespace())
+        {
+            continue;
+        }
+        let mut attrs = Vec::new();
+        return continue_open_tag(tag, &mut attrs, after, registry);
+    }
+    RegionState::Parent
+}
+
+/// Accumulate opening-tag text until `>`, then decide the region's language.
+fn continue_open_tag(
+    tag: HtmlTag,
+    attrs: &mut Vec<u8>,
+    line: &[u8],
+    registry: &LanguageRegistry,
+) -> RegionState {
+    let lower = line.to_ascii_lowercase();
+    let Some(gt) = memchr::memchr(b'>', &lower) else {
+        attrs.extend_from_slice(&lower);
+        attrs.push(b' ');
+        return RegionState::OpenTag {
+            tag,
+            attrs: std::mem::take(attrs),
+        };
+    };
+    attrs.extend_from_slice(&lower[..gt]);
+    // `<script src="x.js"></script>` or a one-line element: nothing embedded follows
+    if find_bytes(&lower[gt..], tag.close()).is_some() {
+        return RegionState::Parent;
+    }
+    RegionState::Element {
+        tag,
+        lang: element_language(tag, attrs, registry),
+    }
+}
+
+/// Language of a `<script>`/`<style>` element from its `lang`/`type` attributes.
+fn element_language(tag: HtmlTag, attrs: &[u8], registry: &LanguageRegistry) -> Option<usize> {
+    if let Some(lang) = attr_value(attrs, b"lang")
+        && let Some(idx) = registry.find_index_by_name(&lang)
+    {
+        return Some(idx);
+    }
+    let default = match tag {
+        HtmlTag::Script => "JavaScript",
+        HtmlTag::Style => "CSS",
+    };
+    match (tag, attr_value(attrs, b"type")) {
+        (HtmlTag::Script, Some(ty)) => {
+            let name = if ty.contains("typescript") {
+                "TypeScript"
+            } else if ty.contains("json") {
+                "JSON"
+            } else if ty.contains("javascript") || ty == "module" || ty.contains("babel") {
+                "JavaScript"
+            } else {
+                // Templates and other opaque payloads stay with the parent
+                return None;
+            };
+            registry.find_index_by_name(name)
+        }
+        _ => registry.find_index_by_name(default),
+    }
+}
 
-    // Line comments
-    let leading = trim_ascii_start(trimmed);
-    for bytes in line_markers {
-        let bytes = bytes.as_slice();
-        if leading.len() >= bytes.len() && &leading[..bytes.len()] == bytes {
-            counts.comment += 1;
-            return;
+/// Value of attribute `name` in lowercase tag text, quoted or bare.
+fn attr_value(attrs: &[u8], name: &[u8]) -> Option<String> {
+    let mut from = 0;
+    while let Some(pos) = find_bytes(&attrs[from..], name) {
+        let at = from + pos;
+        from = at + name.len();
+        let boundary = at == 0 || attrs[at - 1].is_ascii_whitespace();
+        let rest = trim_ascii_start(&attrs[from..]);
+        if !boundary || rest.first() != Some(&b'=') {
+            continue;
         }
+        let rest = trim_ascii_start(&rest[1..]);
+        let value = match rest.first() {
+            Some(&q @ (b'"' | b'\'')) => rest[1..].split(|&b| b == q).next()?,
+            _ => rest
+                .split(|b| b.is_ascii_whitespace() || *b == b'/')
+                .next()?,
+        };
+        return Some(String::from_utf8_lossy(value).into_owned());
+    }
+    None
+}
+
+/// Opening of a Markdown code fence (three or more backticks or tildes) with its info string.
+fn open_fence(line: &[u8], registry: &LanguageRegistry) -> RegionState {
+    let trimmed = trim_ascii_start(line);
+    let Some(&marker) = trimmed.first().filter(|&&b| b == b'`' || b == b'~') else {
+        return RegionState::Parent;
+    };
+    let len = trimmed.iter().take_while(|&&b| b == marker).count();
+    let info = &trimmed[len..];
+    if len < 3 || (marker == b'`' && info.contains(&b'`')) {
+        return RegionState::Parent;
+    }
+    // ```rust, ``` {.python}, ```js title="x"
+    let info = String::from_utf8_lossy(info);
+    let word = info
+        .trim()
+        .trim_start_matches('{')
+        .split(|c: char| c.is_whitespace() || c == ',' || c == '}')
+        .next()
+        .unwrap_or_default();
+    RegionState::Fence {
+        marker,
+        len,
+        lang: (!word.is_empty())
+            .then(|| registry.find_index_by_name(word))
+            .flatten(),
     }
-    counts.code += 1;
 }
 
 #[cfg(test)]
@@ -227,6 +1351,18 @@ mod tests {
     use std::io::Write;
     use tempfile::tempdir;
 
+    fn analyze_file(path: &Path) -> Result<FileCounts> {
+        Analyzer::default().analyze_file(path)
+    }
+
+    fn analyze_file_regions(path: &Path) -> Result<FileAnalysis> {
+        Analyzer::default().analyze_file_regions(path)
+    }
+
+    fn analyze_reader<R: BufRead + ?Sized>(reader: &mut R, path: &Path) -> Result<FileCounts> {
+        Analyzer::default().analyze_reader(reader, path)
+    }
+
     #[test]
     fn rust_line_and_block_comments() {
         let dir = tempdir().unwrap();
@@ -244,6 +1380,210 @@ mod tests {
         assert_eq!(counts.blank, 0);
     }
 
+    #[test]
+    fn rust_nested_block_comments() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("nested.rs");
+        let mut f = std::fs::File::create(&path).unwrap();
+        write!(
+            f,
+            "/* outer /* inner */ still comment */\ncode();\n/* a\n/* b */\nstill comment\n*/\ncode(); /* x /* y */ z */ more();\n"
+        )
+        .unwrap();
+        let counts = analyze_file(&path).unwrap();
+        assert_eq!(counts.total, 7);
+        assert_eq!(counts.comment, 5);
+        assert_eq!(counts.code, 2);
+    }
+
+    #[test]
+    fn c_block_comments_do_not_nest() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("flat.c");
+        let mut f = std::fs::File::create(&path).unwrap();
+        write!(f, "/* outer /* inner */ rest */\n/* a\n/* b */\nint x;\n").unwrap();
+        let counts = analyze_file(&path).unwrap();
+        assert_eq!(counts.total, 4);
+        // The first `*/` closes the comment in C, so the trailing text is code
+        assert_eq!(counts.comment, 2);
+        assert_eq!(counts.code, 2);
+    }
+
+    #[test]
+    fn d_nests_only_plus_comments() {
+        // `/+ +/` nests in D, `/* */` does not, and neither nests in the other
+        let d = "/+ outer /+ inner +/ still comment +/\n/* outer /* inner */ rest */\n/+ a /* b +/\nint x;\n/++ doc /+ nested +/\n still doc +/\n";
+        let counts = analyze_reader(&mut d.as_bytes(), Path::new("app.d")).unwrap();
+        assert_eq!(counts.total, 6);
+        assert_eq!(counts.comment, 2);
+        assert_eq!(counts.doc, 2);
+        assert_eq!(counts.code, 2);
+    }
+
+    #[test]
+    fn doc_comments_counted_separately() {
+        let rs = "//! Crate docs\n/// Item docs\n//// not doc\n// plain\n/** block\n * doc */\n/**/\nfn f() {} /// trailing\n";
+        let counts = analyze_reader(&mut rs.as_bytes(), Path::new("lib.rs")).unwrap();
+        assert_eq!(counts.total, 8);
+        assert_eq!(counts.doc, 4);
+        assert_eq!(counts.comment, 3);
+        assert_eq!(counts.code, 1);
+
+        // Go package comments: only the comment run directly above `package`
+        let go = "// Unrelated\n\n// Package x does things.\n// More.\npackage x\n\n// f is not a package doc\nfunc f() {}\n";
+        let counts = analyze_reader(&mut go.as_bytes(), Path::new("x.go")).unwrap();
+        assert_eq!(counts.doc, 2);
+        assert_eq!(counts.comment, 2);
+        assert_eq!(counts.code, 2);
+        assert_eq!(counts.blank, 2);
+        assert_eq!(
+            counts.total,
+            counts.code + counts.comment + counts.blank + counts.doc
+        );
+    }
+
+    #[test]
+    fn multiple_block_marker_pairs() {
+        let dir = tempdir().unwrap();
+        let pas = dir.path().join("main.pas");
+        let mut f = std::fs::File::create(&pas).unwrap();
+        write!(
+            f,
+            "{{ brace comment }}\n(* paren\n   comment *)\nx := 1; {{ trailing }}\nwriteln('{{ not a comment');\n"
+        )
+        .unwrap();
+        let counts = analyze_file(&pas).unwrap();
+        assert_eq!(counts.total, 5);
+        assert_eq!(counts.comment, 3);
+        assert_eq!(counts.code, 2);
+
+        // The longest opener wins, so `]]` does not close a `--[==[` comment
+        let lua = dir.path().join("init.lua");
+        let mut f = std::fs::File::create(&lua).unwrap();
+        write!(
+            f,
+            "--[==[ long\n]] still comment\n]==]\nprint(\"x\")\n-- line\n"
+        )
+        .unwrap();
+        let counts = analyze_file(&lua).unwrap();
+        assert_eq!(counts.total, 5);
+        assert_eq!(counts.comment, 4);
+        assert_eq!(counts.code, 1);
+    }
+
+    #[test]
+    fn column_one_markers() {
+        // Ruby `=begin`/`=end` only open and close in column 1
+        let rb = "=begin\nx = 1\n  =end\n=end\nputs 1\n  =begin\n";
+        let counts = analyze_reader(&mut rb.as_bytes(), Path::new("a.rb")).unwrap();
+        assert_eq!(counts.comment, 4);
+        assert_eq!(counts.code, 2);
+
+        // Fixed-form Fortran: `C`, `c` or `*` in column 1, `!` anywhere
+        let f = "C comment\n      CALL X\nc lower\n* star\n      Y = 2 * 3\n      ! bang\n";
+        let counts = analyze_reader(&mut f.as_bytes(), Path::new("a.f")).unwrap();
+        assert_eq!(counts.comment, 4);
+        assert_eq!(counts.code, 2);
+
+        // Perl POD runs to `=cut`; text after a column-1 closer stays in the comment
+        let pl = "=head1 NAME\n\nx - demo\n\n=cut\nprint 1;\n";
+        let counts = analyze_reader(&mut pl.as_bytes(), Path::new("a.pl")).unwrap();
+        assert_eq!(counts.doc, 3);
+        assert_eq!(counts.code, 1);
+        let raku = "=begin pod\nDemo.\n=end pod\nsay 1;\n";
+        let counts = analyze_reader(&mut raku.as_bytes(), Path::new("a.raku")).unwrap();
+        assert_eq!(counts.doc, 3);
+        assert_eq!(counts.code, 1);
+    }
+
+    #[test]
+    fn heredoc_bodies_are_code() {
+        let sh = "cat <<-'EOF'\n\t# not a comment\n\tEOF\n# comment\necho \"<<X\"\n";
+        let counts = analyze_reader(&mut sh.as_bytes(), Path::new("a.sh")).unwrap();
+        assert_eq!(counts.code, 4);
+        assert_eq!(counts.comment, 1);
+
+        // The terminator must be the whole word
+        let rb = "x = <<~SQL\n  # still SQL\n  SQLITE\nSQL\n# comment\n";
+        let counts = analyze_reader(&mut rb.as_bytes(), Path::new("a.rb")).unwrap();
+        assert_eq!(counts.code, 4);
+        assert_eq!(counts.comment, 1);
+    }
+
+    #[test]
+    fn doc_opener_alone_on_its_line_is_doc() {
+        let js = "/**\n * Adds.\n */\nfunction add() {}\n/*\n */\n";
+        let counts = analyze_reader(&mut js.as_bytes(), Path::new("a.js")).unwrap();
+        assert_eq!(counts.doc, 3);
+        assert_eq!(counts.comment, 2);
+        assert_eq!(counts.code, 1);
+    }
+
+    #[test]
+    fn rust_markers_inside_literals_are_code() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("strings.rs");
+        let mut f = std::fs::File::create(&path).unwrap();
+        write!(
+            f,
+            "let s = \"/* not a comment\";\nlet t = 1;\nlet u = r#\"// not \"a\" comment\"#;\nlet c = '\"';\n/* real */\nfn f<'a>(x: &'a str) {{}} // lifetime\n"
+        )
+        .unwrap();
+        let counts = analyze_file(&path).unwrap();
+        assert_eq!(counts.total, 6);
+        assert_eq!(counts.comment, 1);
+        assert_eq!(counts.code, 5);
+    }
+
+    #[test]
+    fn javascript_strings_and_template_literals() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("app.js");
+        let mut f = std::fs::File::create(&path).unwrap();
+        write!(
+            f,
+            "const url = \"http://x/*\";\n// comment\nconst t = `multi\n/* still string */\n`;\n"
+        )
+        .unwrap();
+        let counts = analyze_file(&path).unwrap();
+        assert_eq!(counts.total, 5);
+        assert_eq!(counts.comment, 1);
+        assert_eq!(counts.code, 4);
+    }
+
+    #[test]
+    fn unclosed_strings_end_with_their_line() {
+        let count =
+            |text: &str, name: &str| analyze_reader(&mut text.as_bytes(), Path::new(name)).unwrap();
+        // An unterminated string does not swallow the comments below it
+        let counts = count("char *s = \"unterminated\n// comment\nint x;\n", "a.c");
+        assert_eq!((counts.code, counts.comment), (2, 1));
+        // Nor does a quote inside a regex literal
+        let counts = count("const re = /'/;\n/* comment */\nconst x = 1;\n", "a.js");
+        assert_eq!((counts.code, counts.comment), (2, 1));
+        let counts = count("my $x = q{it's};\n# comment\n", "a.pl");
+        assert_eq!((counts.code, counts.comment), (1, 1));
+        // Multiline strings still span lines
+        let counts = count("let s = \"a\n// in string\n\";\n// comment\n", "a.rs");
+        assert_eq!((counts.code, counts.comment), (3, 1));
+    }
+
+    #[test]
+    fn python_hash_inside_strings_is_code() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("hash.py");
+        let mut f = std::fs::File::create(&path).unwrap();
+        write!(
+            f,
+            "s = \"# not a comment\"\n\"\"\"\n# inside string\n\"\"\"\n# real\n"
+        )
+        .unwrap();
+        let counts = analyze_file(&path).unwrap();
+        assert_eq!(counts.total, 5);
+        assert_eq!(counts.comment, 1);
+        assert_eq!(counts.code, 4);
+    }
+
     #[test]
     fn python_triple_quoted_strings_treated_as_code() {
         let dir = tempdir().unwrap();
@@ -265,6 +1605,160 @@ mod tests {
         assert_eq!(counts.code, 4);
     }
 
+    #[test]
+    fn docstrings_as_comments_when_enabled() {
+        // Differently configured analyzers are independent values
+        let docs = Analyzer::builder().docstrings_as_comments(true).build();
+        let plain = Analyzer::default();
+        let py = "\"\"\"Module docstring\nspans lines\n\"\"\"\ndef f():\n    \"\"\"Function doc.\"\"\"\n    x = call(\n        \"\"\"not a docstring\"\"\"\n    )\n    return x\n";
+        let counts = docs
+            .analyze_reader(&mut py.as_bytes(), Path::new("mod.py"))
+            .unwrap();
+        assert_eq!(counts.total, 9);
+        assert_eq!(counts.comment, 4);
+        assert_eq!(counts.code, 5);
+
+        let ex = "defmodule M do\n  @moduledoc \"\"\"\n  Docs\n  \"\"\"\n  def f, do: 1\nend\n";
+        let counts = docs
+            .analyze_bytes(ex.as_bytes(), Path::new("m.ex"))
+            .unwrap();
+        assert_eq!(counts.comment, 3);
+        assert_eq!(counts.code, 3);
+
+        // Off by default: the same docstrings stay code
+        let counts = plain
+            .analyze_reader(&mut py.as_bytes(), Path::new("mod.py"))
+            .unwrap();
+        assert_eq!(counts.comment, 0);
+        assert_eq!(counts.code, 9);
+    }
+
+    #[test]
+    fn flags_generated_and_minified_files() {
+        let analyzer = Analyzer::default();
+        let generated = |content: &str, path: &str| {
+            analyzer
+                .analyze_bytes_regions(content.as_bytes(), Path::new(path))
+                .unwrap()
+                .generated
+        };
+        assert_eq!(
+            generated(
+                "// Code generated by protoc-gen-go. DO NOT EDIT.\npackage pb\n",
+                "a.pb.go"
+            ),
+            Some(GeneratedKind::Marker)
+        );
+        assert_eq!(
+            generated("/**\n * @generated SignedSource<<abc>>\n */\n", "a.js"),
+            Some(GeneratedKind::Marker)
+        );
+        assert_eq!(
+            generated("{}\n", "package-lock.json"),
+            Some(GeneratedKind::Lockfile)
+        );
+        let bundle = "var a=1;function b(c){return c*2+a}".repeat(200);
+        assert_eq!(
+            generated(&format!("/*! lib v1 */\n{bundle}\n"), "app.js"),
+            Some(GeneratedKind::Minified)
+        );
+        // Long lines of prose or filler are not minified code
+        let prose = "The quick brown fox jumps over the lazy dog. ".repeat(50);
+        assert_eq!(generated(&format!("{prose}\n"), "notes.md"), None);
+        assert_eq!(generated(&format!("{}\n", "=".repeat(3000)), "a.txt"), None);
+        assert_eq!(
+            generated(
+                "// <auto-generated>\n//   by a tool\n// </auto-generated>\n",
+                "a.cs"
+            ),
+            Some(GeneratedKind::Marker)
+        );
+        assert_eq!(
+            generated("# Autogenerated by Thrift Compiler\nx = 1\n", "a.py"),
+            Some(GeneratedKind::Marker)
+        );
+        // Markers must be comments, and comments must start with them
+        assert_eq!(generated("AUTOGENERATED_IDS = [1, 2]\n", "ids.py"), None);
+        assert_eq!(
+            generated("const DO_NOT_EDIT: &str = \"@generated\";\n", "a.rs"),
+            None
+        );
+        assert_eq!(
+            generated(
+                "// Please do not edit this without review\nfn a() {}\n",
+                "a.rs"
+            ),
+            None
+        );
+        assert_eq!(
+            generated(
+                "# Skips auto-generated files and @generated headers\n",
+                "a.py"
+            ),
+            None
+        );
+        assert_eq!(
+            generated(
+                "/* Parser for generated code markers (do not edit lightly) */\n",
+                "a.c"
+            ),
+            None
+        );
+        // Markers only count near the top
+        let late = format!(
+            "{}// Code generated by tool. DO NOT EDIT.\n",
+            "fn a() {}\n".repeat(GENERATED_MARKER_LINES)
+        );
+        assert_eq!(generated(&late, "main.rs"), None);
+    }
+
+    #[test]
+    fn transcodes_utf16_and_skips_binary_content() {
+        let analyzer = Analyzer::default();
+        let source = "// comment\r\nclass A { }\r\n\r\n/// <summary>x</summary>\r\n";
+        let mut le = vec![0xFF, 0xFE];
+        let mut be = vec![0xFE, 0xFF];
+        for unit in source.encode_utf16() {
+            le.extend_from_slice(&unit.to_le_bytes());
+            be.extend_from_slice(&unit.to_be_bytes());
+        }
+        let utf8 = analyzer
+            .analyze_bytes(source.as_bytes(), Path::new("A.cs"))
+            .unwrap();
+        assert_eq!((utf8.code, utf8.comment, utf8.blank), (1, 1, 1));
+        for bytes in [&le, &be] {
+            let counts = analyzer.analyze_bytes(bytes, Path::new("A.cs")).unwrap();
+            assert_eq!(counts, utf8);
+        }
+
+        // A UTF-8 BOM does not turn a first-line comment into code
+        let counts = analyzer
+            .analyze_bytes("\u{feff}// c\nfn main() {}\n".as_bytes(), Path::new("a.rs"))
+            .unwrap();
+        assert_eq!((counts.code, counts.comment), (1, 1));
+
+        let analysis = analyzer
+            .analyze_bytes_regions(b"\x7fELF\x02\x01\x01\x00\x00fn\n", Path::new("a.rs"))
+            .unwrap();
+        assert!(analysis.binary);
+        assert_eq!(analysis.combined(), FileCounts::default());
+    }
+
+    #[test]
+    fn mmap_and_buffered_reads_agree() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("big.rs");
+        std::fs::write(&path, "// c\nfn main() {}\n\n/* a\n b */\n".repeat(500)).unwrap();
+        let mapped = Analyzer::builder().mmap_threshold(0).build();
+        let buffered = Analyzer::builder().no_mmap(true).mmap_threshold(0).build();
+        let counts = mapped.analyze_file(&path).unwrap();
+        assert_eq!(counts, buffered.analyze_file(&path).unwrap());
+        assert_eq!(
+            (counts.code, counts.comment, counts.blank),
+            (500, 1500, 500)
+        );
+    }
+
     #[test]
     fn html_block_comments() {
         let dir = tempdir().unwrap();
@@ -305,6 +1799,67 @@ mod tests {
         assert_eq!(counts.code, 2);
     }
 
+    #[test]
+    fn html_script_and_style_regions() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("index.html");
+        let mut f = std::fs::File::create(&path).unwrap();
+        write!(
+            f,
+            "<html>\n<style>\n/* theme */\nbody {{ color: red; }}\n</style>\n<!-- <script>\nnot code -->\n<script src=\"app.js\"></script>\n<script type=\"module\">\n// boot\n\nstart();\n</script>\n<script type=\"text/template\">\n<p>hi</p>\n</script>\n</html>\n"
+        )
+        .unwrap();
+        let a = analyze_file_regions(&path).unwrap();
+        let css = a.embedded["CSS"];
+        assert_eq!((css.files, css.code, css.comment), (0, 1, 1));
+        let js = a.embedded["JavaScript"];
+        assert_eq!((js.files, js.code, js.comment, js.blank), (0, 1, 1, 1));
+        assert_eq!(a.embedded.len(), 2);
+        // Tags, the commented-out script and the template body stay HTML
+        assert_eq!(a.counts.files, 1);
+        assert_eq!(a.counts.comment, 2);
+        assert_eq!(a.counts.code, 10);
+        assert_eq!(a.combined().total, 17);
+        assert_eq!(analyze_file(&path).unwrap(), a.combined());
+    }
+
+    #[test]
+    fn vue_lang_attributes_select_language() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("App.vue");
+        let mut f = std::fs::File::create(&path).unwrap();
+        write!(
+            f,
+            "<template>\n  <div/>\n</template>\n<script\n  setup\n  lang=\"ts\">\nconst n: number = 1\n</script>\n<style lang='scss' scoped>\n.a {{}}\n</style>\n"
+        )
+        .unwrap();
+        let a = analyze_file_regions(&path).unwrap();
+        assert_eq!(a.embedded["TypeScript"].code, 1);
+        assert_eq!(a.embedded["SCSS"].code, 1);
+        assert_eq!(a.counts.code, 9);
+    }
+
+    #[test]
+    fn markdown_code_fences() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("guide.md");
+        let mut f = std::fs::File::create(&path).unwrap();
+        write!(
+            f,
+            "# Guide\n\n```rust\n// demo\nfn main() {{}}\n```\n~~~~ {{.python}}\nx = 1  # one\n```\n~~~~\n```\nplain\n```\n````console\n$ ls\n````\n"
+        )
+        .unwrap();
+        let a = analyze_file_regions(&path).unwrap();
+        let rust = a.embedded["Rust"];
+        assert_eq!((rust.code, rust.comment), (1, 1));
+        // A shorter or different fence does not close the block
+        assert_eq!(a.embedded["Python"].code, 2);
+        assert_eq!(a.embedded["Shell"].code, 1);
+        // Fences without a known language stay Markdown
+        assert_eq!(a.counts.code, 10);
+        assert_eq!(a.counts.blank, 1);
+    }
+
     #[test]
     fn ini_line_comments() {
         let dir = tempdir().unwrap();
@@ -346,4 +1901,38 @@ mod tests {
         // code: xml decl + <svg> and </svg>
         assert_eq!(counts.code, 3);
     }
+
+    #[test]
+    fn classify_lines_reports_each_line_in_order() {
+        let src = "// Package demo does things.\n// More.\npackage demo\n\n// helper\n/* block\n*/\nfunc f() {} // trailing\n";
+        let mut kinds = Vec::new();
+        Analyzer::default()
+            .classify_lines(&mut src.as_bytes(), Path::new("demo.go"), |n, k| {
+                kinds.push((n, k))
+            })
+            .unwrap();
+        use LineKind::*;
+        assert_eq!(
+            kinds,
+            vec![
+                (1, Doc),
+                (2, Doc),
+                (3, Code),
+                (4, Blank),
+                (5, Comment),
+                (6, Comment),
+                (7, Comment),
+                (8, Code),
+            ]
+        );
+
+        let mut counts = FileCounts::one_file();
+        for (_, k) in &kinds {
+            count_line(&mut counts, *k);
+        }
+        assert_eq!(
+            counts,
+            analyze_reader(&mut src.as_bytes(), Path::new("demo.go")).unwrap()
+        );
+    }
 }
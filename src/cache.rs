@@ -0,0 +1,517 @@
+use std::collections::HashMap;
+use std::fs::{self, Metadata};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::UNIX_EPOCH;
+
+use anyhow::{Context, Result};
+use git2::{Oid, Repository};
+use serde::{Deserialize, Serialize};
+
+use crate::analyzer::{ANALYZER_VERSION, Analyzer, is_lockfile};
+use crate::languages::{Detection, DetectionSource};
+use crate::types::FileAnalysis;
+
+/// Directory inside the git directory (`.git/` or a bare repository) holding cache files.
+pub const CACHE_DIR: &str = "ocloc-cache";
+
+/// A blob's language, how it was detected, and its analysis. `language` is `None` when it
+/// is unknown; such blobs are counted as plain text and never cached.
+#[derive(Debug, Clone)]
+pub struct BlobAnalysis {
+    pub language: Option<String>,
+    pub detected_by: Option<DetectionSource>,
+    pub analysis: FileAnalysis,
+    pub bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Entry {
+    analysis: FileAnalysis,
+    bytes: u64,
+}
+
+/// Blob analyses keyed by (blob id, language, lockfile name), for one analyzer
+/// configuration.
+///
+/// A blob's counts depend only on its content, its language and the analyzer, so they are
+/// shared between runs of `diff`, `--rev` and `history`; only its `generated` status also
+/// depends on the path, through the lockfile name. Each configuration (counting rules
+/// version, language definitions, docstring policy) has its own file under
+/// `<git dir>/ocloc-cache/`, loaded on open and written back by `save`.
+pub struct BlobCache {
+    file: Option<PathBuf>,
+    entries: Mutex<HashMap<String, Entry>>,
+    dirty: Mutex<bool>,
+}
+
+impl BlobCache {
+    /// Cache for `repo` and `analyzer`'s configuration. A missing or unreadable cache file
+    /// starts empty.
+    pub fn open(repo: &Repository, analyzer: &Analyzer) -> Self {
+        let file = repo
+            .path()
+            .join(CACHE_DIR)
+            .join(format!("{}.json", analyzer_key(analyzer)));
+        let entries = fs::read(&file)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .unwrap_or_default();
+        Self {
+            file: Some(file),
+            entries: Mutex::new(entries),
+            dirty: Mutex::new(false),
+        }
+    }
+
+    /// Cache kept in memory only (`--no-cache`): blobs are still analyzed once per run.
+    pub fn in_memory() -> Self {
+        Self {
+            file: None,
+            entries: Mutex::new(HashMap::new()),
+            dirty: Mutex::new(false),
+        }
+    }
+
+    /// Analysis of blob `oid` found at `path`. The blob is only read when the cache has no
+    /// entry or the language depends on its content; `None` if it cannot be read.
+    pub fn analyze_blob(
+        &self,
+        analyzer: &Analyzer,
+        repo: &Repository,
+        path: &Path,
+        oid: Oid,
+    ) -> Option<BlobAnalysis> {
+        self.analyze_blob_as(analyzer, repo, path, oid, None)
+    }
+
+    /// Like `analyze_blob`, with `language` (a `linguist-language` attribute) overriding
+    /// detection when it names a known language.
+    pub fn analyze_blob_as(
+        &self,
+        analyzer: &Analyzer,
+        repo: &Repository,
+        path: &Path,
+        oid: Oid,
+        language: Option<&str>,
+    ) -> Option<BlobAnalysis> {
+        let registry = analyzer.registry();
+        let lockfile = is_lockfile(path);
+        let forced = language
+            .and_then(|name| registry.find_index_by_name(name))
+            .map(|index| Detection {
+                index,
+                source: DetectionSource::Attribute,
+            });
+        if let Some(d) = forced.or_else(|| registry.detect_by_name(path)) {
+            if let Some(hit) = self.get(oid, &registry[d.index].name, lockfile) {
+                return Some(BlobAnalysis {
+                    detected_by: Some(d.source),
+                    ..hit
+                });
+            }
+        }
+
+        let blob = repo.find_blob(oid).ok()?;
+        let content = blob.content();
+        let bytes = content.len() as u64;
+        let Some(detection) = forced.or_else(|| registry.detect(path, Some(content))) else {
+            let analysis = analyzer
+                .analyze_bytes_regions(content, path)
+                .unwrap_or_default();
+            return Some(BlobAnalysis {
+                language: None,
+                detected_by: None,
+                analysis,
+                bytes,
+            });
+        };
+        let language = &registry[detection.index].name;
+        if let Some(hit) = self.get(oid, language, lockfile) {
+            return Some(BlobAnalysis {
+                detected_by: Some(detection.source),
+                ..hit
+            });
+        }
+        let analysis = analyzer
+            .analyze_reader_regions_as(&mut &content[..], path, detection.index)
+            .unwrap_or_default();
+        let entry = Entry { analysis, bytes };
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.insert(entry_key(oid, language, lockfile), entry.clone());
+            if let Ok(mut dirty) = self.dirty.lock() {
+                *dirty = true;
+            }
+        }
+        Some(BlobAnalysis {
+            language: Some(language.clone()),
+            detected_by: Some(detection.source),
+            analysis: entry.analysis,
+            bytes,
+        })
+    }
+
+    fn get(&self, oid: Oid, language: &str, lockfile: bool) -> Option<BlobAnalysis> {
+        let entries = self.entries.lock().ok()?;
+        let entry = entries.get(&entry_key(oid, language, lockfile))?;
+        Some(BlobAnalysis {
+            language: Some(language.to_string()),
+            detected_by: None,
+            analysis: entry.analysis.clone(),
+            bytes: entry.bytes,
+        })
+    }
+
+    /// Write new entries back to disk (atomically, via a temporary file).
+    pub fn save(&self) -> Result<()> {
+        let Some(file) = &self.file else {
+            return Ok(());
+        };
+        if !self.dirty.lock().map(|d| *d).unwrap_or(false) {
+            return Ok(());
+        }
+        let entries = self
+            .entries
+            .lock()
+            .map_err(|_| anyhow::anyhow!("cache lock poisoned"))?;
+        let dir = file.parent().unwrap_or(Path::new("."));
+        fs::create_dir_all(dir).with_context(|| format!("create {}", dir.display()))?;
+        let tmp = file.with_extension(format!("tmp{}", std::process::id()));
+        fs::write(&tmp, serde_json::to_vec(&*entries)?)
+            .with_context(|| format!("write {}", tmp.display()))?;
+        fs::rename(&tmp, file).with_context(|| format!("write {}", file.display()))?;
+        Ok(())
+    }
+}
+
+/// What `prune` removed.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct PruneStats {
+    pub files: usize,
+    pub entries: usize,
+}
+
+/// Remove cache files written by other counting rules versions, and entries whose blob is no longer
+/// in the object database. With `all`, remove the whole cache.
+pub fn prune(repo: &Repository, all: bool) -> Result<PruneStats> {
+    let dir = repo.path().join(CACHE_DIR);
+    let mut stats = PruneStats::default();
+    if !dir.is_dir() {
+        return Ok(stats);
+    }
+    let version_prefix = format!("v{ANALYZER_VERSION}-");
+    let odb = repo.odb().context("open object database")?;
+    for dent in fs::read_dir(&dir).with_context(|| format!("read {}", dir.display()))? {
+        let path = dent?.path();
+        let name = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or_default();
+        if all || !name.starts_with(&version_prefix) || !name.ends_with(".json") {
+            fs::remove_file(&path).with_context(|| format!("remove {}", path.display()))?;
+            stats.files += 1;
+            continue;
+        }
+        let Ok(mut entries) = fs::read(&path)
+            .map_err(anyhow::Error::from)
+            .and_then(|b| Ok(serde_json::from_slice::<HashMap<String, Entry>>(&b)?))
+        else {
+            fs::remove_file(&path).with_context(|| format!("remove {}", path.display()))?;
+            stats.files += 1;
+            continue;
+        };
+        let before = entries.len();
+        entries.retain(|key, _| {
+            let oid = key.split(':').next().and_then(|h| Oid::from_str(h).ok());
+            oid.is_some_and(|oid| odb.exists(oid))
+        });
+        if entries.len() < before {
+            stats.entries += before - entries.len();
+            fs::write(&path, serde_json::to_vec(&entries)?)
+                .with_context(|| format!("write {}", path.display()))?;
+        }
+    }
+    if all {
+        fs::remove_dir(&dir).ok();
+    }
+    Ok(stats)
+}
+
+/// What identifies a file's content without reading it: size, modification time and inode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileStamp {
+    pub size: u64,
+    pub mtime_ns: u128,
+    pub inode: u64,
+}
+
+impl FileStamp {
+    pub fn of(meta: &Metadata) -> Self {
+        #[cfg(unix)]
+        let inode = std::os::unix::fs::MetadataExt::ino(meta);
+        #[cfg(not(unix))]
+        let inode = 0;
+        Self {
+            size: meta.len(),
+            mtime_ns: meta
+                .modified()
+                .ok()
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map_or(0, |d| d.as_nanos()),
+            inode,
+        }
+    }
+}
+
+/// A scanned file: its language (registry index and how it was found, `None` when
+/// unknown) and, once analyzed, its counts. `attr_language` is the `linguist-language`
+/// override in effect.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanEntry {
+    pub stamp: FileStamp,
+    pub attr_language: Option<String>,
+    pub detection: Option<Detection>,
+    pub analysis: Option<FileAnalysis>,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct ScanFile {
+    analyzer: String,
+    files: HashMap<String, ScanEntry>,
+}
+
+/// Per-file results of a directory scan (`--scan-cache`), keyed by path relative to the
+/// scanned root. An entry is reused while the file's size, mtime and inode are unchanged;
+/// the whole cache is dropped when the counting rules or language configuration change.
+/// Only files seen during the run are written back, so deleted files fall out.
+pub struct ScanCache {
+    file: PathBuf,
+    analyzer: String,
+    previous: HashMap<String, ScanEntry>,
+    current: Mutex<HashMap<String, ScanEntry>>,
+}
+
+impl ScanCache {
+    /// Load the cache at `file`; missing, unreadable or outdated caches start empty.
+    pub fn open(file: PathBuf, analyzer: &Analyzer) -> Self {
+        let key = analyzer_key(analyzer);
+        let previous = fs::read(&file)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice::<ScanFile>(&bytes).ok())
+            .filter(|f| f.analyzer == key)
+            .map(|f| f.files)
+            .unwrap_or_default();
+        Self {
+            file,
+            analyzer: key,
+            previous,
+            current: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The entry for `path` if the file is unchanged and has the same language override.
+    pub fn lookup(
+        &self,
+        path: &str,
+        stamp: FileStamp,
+        attr_language: Option<&str>,
+    ) -> Option<ScanEntry> {
+        let entry = self.previous.get(path)?;
+        if entry.stamp != stamp || entry.attr_language.as_deref() != attr_language {
+            return None;
+        }
+        if let Ok(mut current) = self.current.lock() {
+            current.insert(path.to_string(), entry.clone());
+        }
+        Some(entry.clone())
+    }
+
+    /// Record what was found for `path` in this run.
+    pub fn store(&self, path: &str, entry: ScanEntry) {
+        if let Ok(mut current) = self.current.lock() {
+            current.insert(path.to_string(), entry);
+        }
+    }
+
+    /// Write the entries of this run to the cache file (atomically, via a temporary file).
+    pub fn save(self) -> Result<()> {
+        let files = self
+            .current
+            .into_inner()
+            .map_err(|_| anyhow::anyhow!("cache lock poisoned"))?;
+        let data = ScanFile {
+            analyzer: self.analyzer,
+            files,
+        };
+        if let Some(dir) = self.file.parent() {
+            fs::create_dir_all(dir).with_context(|| format!("create {}", dir.display()))?;
+        }
+        let tmp = self
+            .file
+            .with_extension(format!("tmp{}", std::process::id()));
+        fs::write(&tmp, serde_json::to_vec(&data)?)
+            .with_context(|| format!("write {}", tmp.display()))?;
+        fs::rename(&tmp, &self.file).with_context(|| format!("write {}", self.file.display()))?;
+        Ok(())
+    }
+}
+
+/// Default scan cache file for the directory `root`:
+/// `$XDG_CACHE_HOME/ocloc/scan/<hash of the canonical path>.json` (`~/.cache` by default).
+pub fn default_scan_cache_path(root: &Path) -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_CACHE_HOME")
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".cache")))?;
+    let root = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+    let hash = fnv1a(root.as_os_str().as_encoded_bytes(), FNV_OFFSET);
+    Some(
+        base.join("ocloc")
+            .join("scan")
+            .join(format!("{hash:016x}.json")),
+    )
+}
+
+fn entry_key(oid: Oid, language: &str, lockfile: bool) -> String {
+    if lockfile {
+        format!("{oid}:{language}:lockfile")
+    } else {
+        format!("{oid}:{language}")
+    }
+}
+
+/// Analyzer configuration in a cache file name: the counting rules version, then a hash of
+/// the language definitions (as JSON) and the options that change counts.
+fn analyzer_key(analyzer: &Analyzer) -> String {
+    let specs = serde_json::to_vec(analyzer.registry().specs()).unwrap_or_default();
+    let hash = fnv1a(&specs, FNV_OFFSET);
+    let hash = fnv1a(&[u8::from(analyzer.options().docstrings_as_comments)], hash);
+    format!("v{ANALYZER_VERSION}-{hash:016x}")
+}
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+
+/// 64-bit FNV-1a of `bytes`, continuing from `hash`: unlike `DefaultHasher`, stable across
+/// Rust releases and platforms, so cache file names survive toolchain upgrades.
+fn fnv1a(bytes: &[u8], hash: u64) -> u64 {
+    bytes.iter().fold(hash, |h, &b| {
+        (h ^ u64::from(b)).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::GeneratedKind;
+    use std::process::Command;
+
+    fn git(root: &Path, args: &[&str]) {
+        assert!(
+            Command::new("git")
+                .args(["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+                .args(args)
+                .current_dir(root)
+                .status()
+                .unwrap()
+                .success()
+        );
+    }
+
+    #[test]
+    fn entries_persist_per_configuration_and_prune() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        git(root, &["init", "-q"]);
+        std::fs::write(root.join("a.rs"), "// a\nfn a() {}\n").unwrap();
+        git(root, &["add", "."]);
+        git(root, &["commit", "-q", "-m", "one"]);
+        let repo = Repository::open(root).unwrap();
+        let oid = repo
+            .head()
+            .unwrap()
+            .peel_to_tree()
+            .unwrap()
+            .get_name("a.rs")
+            .unwrap()
+            .id();
+
+        let analyzer = Analyzer::default();
+        let cache = BlobCache::open(&repo, &analyzer);
+        let first = cache
+            .analyze_blob(&analyzer, &repo, Path::new("a.rs"), oid)
+            .unwrap();
+        assert_eq!(first.language.as_deref(), Some("Rust"));
+        assert_eq!(first.analysis.counts.comment, 1);
+        cache.save().unwrap();
+
+        // A fresh cache answers from disk, without the blob
+        let cache = BlobCache::open(&repo, &analyzer);
+        assert_eq!(
+            cache.get(oid, "Rust", false).unwrap().analysis.counts.code,
+            1
+        );
+        // Other settings use another file
+        let docstrings = Analyzer::builder().docstrings_as_comments(true).build();
+        assert!(
+            BlobCache::open(&repo, &docstrings)
+                .get(oid, "Rust", false)
+                .is_none()
+        );
+
+        let cache_dir = repo.path().join(CACHE_DIR);
+        std::fs::write(cache_dir.join("v0-0000000000000000.json"), "{}").unwrap();
+        let stale = format!(
+            "{}:Rust",
+            Oid::hash_object(git2::ObjectType::Blob, b"gone").unwrap()
+        );
+        let file = cache_dir.join(format!("{}.json", analyzer_key(&analyzer)));
+        let mut entries: HashMap<String, Entry> =
+            serde_json::from_slice(&std::fs::read(&file).unwrap()).unwrap();
+        let kept = entries.values().next().unwrap().clone();
+        entries.insert(stale, kept);
+        std::fs::write(&file, serde_json::to_vec(&entries).unwrap()).unwrap();
+
+        assert_eq!(
+            prune(&repo, false).unwrap(),
+            PruneStats {
+                files: 1,
+                entries: 1
+            }
+        );
+        assert!(
+            BlobCache::open(&repo, &analyzer)
+                .get(oid, "Rust", false)
+                .is_some()
+        );
+        assert_eq!(prune(&repo, true).unwrap().files, 1);
+        assert!(!cache_dir.exists());
+    }
+
+    #[test]
+    fn lockfile_status_follows_the_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        git(root, &["init", "-q"]);
+        let toml = "[[package]]\nname = \"a\"\n";
+        std::fs::write(root.join("deps.toml"), toml).unwrap();
+        std::fs::write(root.join("Cargo.lock"), toml).unwrap();
+        git(root, &["add", "."]);
+        git(root, &["commit", "-q", "-m", "one"]);
+        let repo = Repository::open(root).unwrap();
+        let oid = Oid::hash_object(git2::ObjectType::Blob, toml.as_bytes()).unwrap();
+
+        let analyzer = Analyzer::default();
+        let cache = BlobCache::in_memory();
+        let analyze = |path: &str| {
+            cache
+                .analyze_blob(&analyzer, &repo, Path::new(path), oid)
+                .unwrap()
+                .analysis
+                .generated
+        };
+        // Same blob and language, so only the name tells them apart
+        assert_eq!(analyze("deps.toml"), None);
+        assert_eq!(analyze("Cargo.lock"), Some(GeneratedKind::Lockfile));
+        assert_eq!(analyze("deps.toml"), None);
+    }
+}
@@ -1,10 +1,23 @@
+use std::collections::HashSet;
 use std::path::PathBuf;
+use std::sync::Arc;
 
 use anyhow::Result;
 use clap::{ArgAction, Parser, ValueHint};
+use git2::Repository;
 
+use crate::analyzer::Analyzer;
+use crate::cache::BlobCache;
+use crate::languages::{LanguageRegistry, RegistryBuilder, user_languages_path};
+
+mod explain;
+mod history;
+mod prune;
+mod rev;
 mod run_impl;
 mod sub_diff;
+mod tally;
+mod tree;
 
 #[derive(Parser, Debug, Clone)]
 #[command(name = "ocloc", version, about = "Fast, reliable lines-of-code counter", long_about = None)]
@@ -69,6 +82,73 @@ pub struct Args {
     #[arg(long = "no-mmap", action = ArgAction::SetTrue)]
     pub no_mmap: bool,
 
+    /// Count docstrings (e.g. Python triple-quoted strings opening a block) as comments
+    #[arg(long = "docstrings-as-comments", action = ArgAction::SetTrue)]
+    pub docstrings_as_comments: bool,
+
+    /// Extra language definitions (JSON list, same schema as the built-in languages);
+    /// repeatable, later files win
+    #[arg(long = "lang-file", value_name = "PATH", value_hint = ValueHint::FilePath)]
+    pub lang_files: Vec<PathBuf>,
+
+    /// Count generated files (`linguist-generated`, "DO NOT EDIT" markers, minified
+    /// content, lockfiles) and `linguist-vendored` files like any other (excluded by default)
+    #[arg(long = "include-generated", action = ArgAction::SetTrue)]
+    pub include_generated: bool,
+
+    /// List every counted file with its language, line counts and size
+    #[arg(long = "by-file", action = ArgAction::SetTrue, conflicts_with = "ultra")]
+    pub by_file: bool,
+
+    /// Column to sort --by-file rows by (numbers descending, names ascending)
+    #[arg(long = "sort", value_name = "COLUMN", value_enum, default_value_t = SortKey::Code)]
+    pub sort: SortKey,
+
+    /// Only list the first N files after sorting
+    #[arg(long = "top", value_name = "N")]
+    pub top: Option<usize>,
+
+    /// Group totals by directory, with a per-language breakdown for each
+    #[arg(long = "by-dir", action = ArgAction::SetTrue, conflicts_with = "ultra")]
+    pub by_dir: bool,
+
+    /// Directory levels kept by --by-dir (`--depth 2` groups `services/auth/...`)
+    #[arg(
+        long = "depth",
+        value_name = "N",
+        default_value_t = 1,
+        requires = "by_dir"
+    )]
+    pub depth: usize,
+
+    /// Group totals by owner from CODEOWNERS (in PATH, .github/ or docs/)
+    #[arg(long = "by-owner", action = ArgAction::SetTrue, conflicts_with = "ultra")]
+    pub by_owner: bool,
+
+    /// Count the files of a git revision (commit, tag or branch) straight from the object
+    /// database, without a checkout; PATH may be a bare repository
+    #[arg(long = "rev", value_name = "REV", conflicts_with = "explain")]
+    pub rev: Option<String>,
+
+    /// Do not read or write the blob cache in the git directory (`--rev`)
+    #[arg(long = "no-cache", action = ArgAction::SetTrue)]
+    pub no_cache: bool,
+
+    /// Reuse counts of files unchanged (size, mtime, inode) since the last scan, from a
+    /// cache at PATH or under `$XDG_CACHE_HOME/ocloc/scan/`
+    #[arg(
+        long = "scan-cache",
+        value_name = "PATH",
+        num_args = 0..=1,
+        value_hint = ValueHint::FilePath,
+        conflicts_with = "rev"
+    )]
+    pub scan_cache: Option<Option<PathBuf>>,
+
+    /// Print FILE's detected language and each line's classification (code, comment, doc, blank) and exit
+    #[arg(long = "explain", value_name = "FILE", value_hint = ValueHint::FilePath)]
+    pub explain: Option<PathBuf>,
+
     /// Ultra-fast mode: prioritize speed over details
     /// - Disables progress and per-language aggregation
     /// - Minimizes metadata calls
@@ -77,20 +157,171 @@ pub struct Args {
     pub ultra: bool,
 }
 
+/// Column to sort per-file rows by.
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortKey {
+    Path,
+    Language,
+    Code,
+    Comment,
+    Blank,
+    Doc,
+    Total,
+    Bytes,
+}
+
 pub fn run() -> Result<()> {
     let args = Args::parse();
     if let Some(cmd) = &args.cmd {
         match cmd {
             Subcommand::Diff(diff_args) => return sub_diff::run_diff(diff_args),
+            Subcommand::History(history_args) => return history::run_history(history_args),
+            Subcommand::Cache(CacheArgs {
+                action: CacheAction::Prune(prune_args),
+            }) => return prune::run_prune(prune_args),
         }
     }
+    if let Some(file) = &args.explain {
+        return explain::run_explain(&args.analyzer()?, file);
+    }
+    if args.rev.is_some() {
+        return rev::run_rev(args);
+    }
     run_impl::run_with_args(args)
 }
 
+impl Args {
+    /// `--ext` as lowercase extensions without dots
+    pub fn allowed_exts(&self) -> Option<HashSet<String>> {
+        self.extensions.as_deref().map(parse_extensions)
+    }
+
+    /// Analyzer configured from the mmap, docstring and language flags
+    pub fn analyzer(&self) -> Result<Analyzer> {
+        let mut builder = Analyzer::builder()
+            .no_mmap(self.no_mmap)
+            .docstrings_as_comments(self.docstrings_as_comments)
+            .registry(load_registry(&self.lang_files)?);
+        if let Some(bytes) = self.mmap_large {
+            builder = builder.mmap_threshold(bytes);
+        }
+        Ok(builder.build())
+    }
+}
+
+/// Parse an `--ext` list (`rs,py,.js`) into lowercase extensions without dots.
+pub fn parse_extensions(list: &str) -> HashSet<String> {
+    list.split(',')
+        .filter(|t| !t.trim().is_empty())
+        .map(|t| t.trim().trim_start_matches('.').to_ascii_lowercase())
+        .collect()
+}
+
+/// Built-in languages merged with the per-user config file and `--lang-file` definitions.
+/// Extensions reassigned between languages are reported on stderr.
+pub fn load_registry(lang_files: &[PathBuf]) -> Result<Arc<LanguageRegistry>> {
+    let user = user_languages_path().filter(|p| p.is_file());
+    if user.is_none() && lang_files.is_empty() {
+        return Ok(LanguageRegistry::builtin());
+    }
+    let mut builder = RegistryBuilder::default();
+    for path in user.iter().chain(lang_files) {
+        builder.add_file(path)?;
+    }
+    for conflict in builder.conflicts() {
+        eprintln!("warning: {}", conflict);
+    }
+    Ok(Arc::new(builder.build()))
+}
+
+/// The on-disk blob cache of `repo`, or a run-local one with `--no-cache`.
+fn open_cache(repo: &Repository, analyzer: &Analyzer, no_cache: bool) -> BlobCache {
+    if no_cache {
+        BlobCache::in_memory()
+    } else {
+        BlobCache::open(repo, analyzer)
+    }
+}
+
 #[derive(clap::Subcommand, Debug, Clone)]
 pub enum Subcommand {
     /// Show LOC deltas between two git refs or working tree
     Diff(DiffArgs),
+    /// Count lines at commits along the first-parent history, as a time series
+    History(HistoryArgs),
+    /// Manage the blob cache used by `diff`, `--rev` and `history`
+    Cache(CacheArgs),
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct CacheArgs {
+    #[command(subcommand)]
+    pub action: CacheAction,
+}
+
+#[derive(clap::Subcommand, Debug, Clone)]
+pub enum CacheAction {
+    /// Delete cache files of other counting rules versions and entries for blobs no longer
+    /// in the repository
+    Prune(PruneArgs),
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct PruneArgs {
+    /// Repository whose cache to prune (work tree or bare)
+    #[arg(value_name = "PATH", default_value = ".", value_hint = ValueHint::DirPath)]
+    pub path: PathBuf,
+
+    /// Remove the whole cache
+    #[arg(long = "all", action = ArgAction::SetTrue)]
+    pub all: bool,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct HistoryArgs {
+    /// Repository to read (work tree or bare)
+    #[arg(value_name = "PATH", default_value = ".", value_hint = ValueHint::DirPath)]
+    pub path: PathBuf,
+
+    /// Oldest commit to include (defaults to the root commit)
+    #[arg(long, value_name = "REV")]
+    pub from: Option<String>,
+
+    /// Newest commit to include
+    #[arg(long, value_name = "REV", default_value = "HEAD")]
+    pub to: String,
+
+    /// Sample every N commits (the newest commit is always included)
+    #[arg(long, value_name = "N", conflicts_with = "every_days")]
+    pub every: Option<usize>,
+
+    /// Sample at most one commit per N days (the newest commit is always included)
+    #[arg(long = "every-days", value_name = "N")]
+    pub every_days: Option<u32>,
+
+    /// Output JSON
+    #[arg(long = "json", action = ArgAction::SetTrue, conflicts_with = "csv")]
+    pub json: bool,
+
+    /// Output CSV (one row per commit and language, plus a Total row per commit)
+    #[arg(long = "csv", action = ArgAction::SetTrue, conflicts_with = "json")]
+    pub csv: bool,
+
+    /// Count generated files (marker comments, minified content, lockfiles) like any other
+    #[arg(long = "include-generated", action = ArgAction::SetTrue)]
+    pub include_generated: bool,
+
+    /// Limit by comma-separated extensions (no dots)
+    #[arg(long = "ext", value_name = "LIST")]
+    pub extensions: Option<String>,
+
+    /// Extra language definitions (JSON list); repeatable, later files win
+    #[arg(long = "lang-file", value_name = "PATH", value_hint = ValueHint::FilePath)]
+    pub lang_files: Vec<PathBuf>,
+
+    /// Do not read or write the blob cache in the git directory
+    #[arg(long = "no-cache", action = ArgAction::SetTrue)]
+    pub no_cache: bool,
 }
 
 #[derive(clap::Args, Debug, Clone)]
@@ -131,11 +362,17 @@ pub struct DiffArgs {
     #[arg(long = "by-file", action = ArgAction::SetTrue)]
     pub by_file: bool,
 
+    /// Count lines added and removed in the diff hunks (churn), each classified as code,
+    /// comment, doc or blank, instead of net changes in the file counts
+    #[arg(long = "hunks", action = ArgAction::SetTrue)]
+    pub hunks: bool,
+
     /// Summary only: hide per-file details in outputs
     #[arg(long = "summary-only", action = ArgAction::SetTrue)]
     pub summary_only: bool,
 
-    /// Fail if code added exceeds this threshold
+    /// Fail if code added (the net delta, or the lines added in the hunks with --hunks)
+    /// exceeds this threshold
     #[arg(long = "max-code-added")]
     pub max_code_added: Option<usize>,
 
@@ -158,4 +395,12 @@ pub struct DiffArgs {
     /// Limit by comma-separated extensions (no dots)
     #[arg(long = "ext", value_name = "LIST")]
     pub extensions: Option<String>,
+
+    /// Extra language definitions (JSON list); repeatable, later files win
+    #[arg(long = "lang-file", value_name = "PATH", value_hint = ValueHint::FilePath)]
+    pub lang_files: Vec<PathBuf>,
+
+    /// Do not read or write the blob cache in the git directory
+    #[arg(long = "no-cache", action = ArgAction::SetTrue)]
+    pub no_cache: bool,
 }
@@ -0,0 +1,47 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+
+use crate::analyzer::{Analyzer, LineKind, decode_text};
+
+/// Print the detected language and how it was found, then every line of `path` prefixed
+/// with its line number and classification.
+pub fn run_explain(analyzer: &Analyzer, path: &Path) -> Result<()> {
+    let bytes = std::fs::read(path).with_context(|| format!("read file: {}", path.display()))?;
+    let registry = analyzer.registry();
+    // Lines are shown, and the language detected, as the classifier reads them: transcoded
+    // from UTF-16, without a BOM
+    let text = decode_text(&bytes);
+    match registry.detect(path, Some(text.as_deref().unwrap_or(&bytes))) {
+        Some(d) => println!(
+            "{}: {} (by {})",
+            path.display(),
+            registry[d.index].name,
+            d.source
+        ),
+        None => println!("{}: unknown language", path.display()),
+    }
+    let Some(text) = text else {
+        println!("binary content (NUL byte near the start): not counted");
+        return Ok(());
+    };
+    let mut kinds: Vec<LineKind> = Vec::new();
+    analyzer.classify_lines(&mut &text[..], path, |n, kind| {
+        // Held comment lines (e.g. Go package docs) are reported after the lines below them
+        if kinds.len() < n {
+            kinds.resize(n, LineKind::Blank);
+        }
+        kinds[n - 1] = kind;
+    })?;
+
+    let width = kinds.len().to_string().len();
+    let mut out = String::new();
+    for (i, (kind, line)) in kinds.iter().zip(text.split(|&b| b == b'\n')).enumerate() {
+        let line = String::from_utf8_lossy(line.strip_suffix(b"\r").unwrap_or(line));
+        let line = format!("{:>width$}  {:<7}  {}", i + 1, kind, line);
+        out.push_str(line.trim_end());
+        out.push('\n');
+    }
+    print!("{}", out);
+    Ok(())
+}
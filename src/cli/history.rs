@@ -0,0 +1,161 @@
+use std::fmt::Write as _;
+use std::path::Path;
+use std::sync::Mutex;
+
+use anyhow::Result;
+use chrono::{DateTime, SecondsFormat};
+use git2::Oid;
+
+use crate::analyzer::Analyzer;
+use crate::types::{FileCounts, HistoryPoint};
+use crate::vcs::VcsContext;
+
+use super::tally::TallyOptions;
+use super::tree::TreeCount;
+use super::{HistoryArgs, SortKey, load_registry, open_cache, parse_extensions};
+
+/// `ocloc history`: count the tree of each sampled commit on the first-parent chain.
+pub fn run_history(args: &HistoryArgs) -> Result<()> {
+    let vcs = VcsContext::open(&args.path)?;
+    let commits = vcs.first_parent_commits(args.from.as_deref(), &args.to)?;
+    let samples = sample(&commits, args.every, args.every_days);
+    let analyzer = Analyzer::builder()
+        .registry(load_registry(&args.lang_files)?)
+        .build();
+    let allowed_exts = args.extensions.as_deref().map(parse_extensions);
+    let opts = TallyOptions {
+        include_generated: args.include_generated,
+        ultra: false,
+        by_file: false,
+        by_dir: None,
+        codeowners: None,
+        sort: SortKey::Code,
+        top: None,
+        skip_empty: false,
+    };
+
+    let cache = open_cache(&vcs.repo, &analyzer, args.no_cache);
+    let counter = TreeCount {
+        analyzer: &analyzer,
+        cache: &cache,
+        opts: &opts,
+        allowed_exts: allowed_exts.as_ref(),
+        min_size: None,
+        max_size: None,
+        seen: Some(Mutex::default()),
+    };
+    let mut points = Vec::with_capacity(samples.len());
+    for &(commit, time) in &samples {
+        let rev = commit.to_string();
+        let files = vcs.tree_files(&rev, Path::new(""))?;
+        let tally = counter.count(&vcs, &rev, Path::new(""), &files)?;
+        let result = tally.finish(&opts, files.len(), 0.0, String::new());
+        points.push(HistoryPoint {
+            commit: commit.to_string(),
+            date: DateTime::from_timestamp(time, 0)
+                .map(|d| d.to_rfc3339_opts(SecondsFormat::Secs, true))
+                .unwrap_or_default(),
+            totals: result.totals,
+            languages: result.per_lang,
+        });
+    }
+
+    cache.save()?;
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&points)?);
+    } else if args.csv {
+        print!("{}", format_csv(&points));
+    } else {
+        print!("{}", format_table(&points));
+    }
+    Ok(())
+}
+
+/// Commits to count, oldest first: all of them, every `every`-th from the oldest, or at
+/// most one per `every_days` days. The newest commit is always kept.
+fn sample(
+    commits: &[(Oid, i64)],
+    every: Option<usize>,
+    every_days: Option<u32>,
+) -> Vec<(Oid, i64)> {
+    let mut out = Vec::new();
+    let mut last_kept: Option<i64> = None;
+    for (i, &(oid, time)) in commits.iter().enumerate() {
+        let keep = i + 1 == commits.len()
+            || match (every, every_days) {
+                (Some(n), _) => i % n.max(1) == 0,
+                (None, Some(days)) => {
+                    last_kept.is_none_or(|t| time - t >= i64::from(days) * 86_400)
+                }
+                (None, None) => true,
+            };
+        if keep {
+            out.push((oid, time));
+            last_kept = Some(time);
+        }
+    }
+    out
+}
+
+fn format_csv(points: &[HistoryPoint]) -> String {
+    let mut out = String::from("commit,date,language,files,code,comment,blank,total,doc\n");
+    let mut push = |p: &HistoryPoint, lang: &str, c: &FileCounts| {
+        let _ = writeln!(
+            out,
+            "{},{},{},{},{},{},{},{},{}",
+            p.commit, p.date, lang, c.files, c.code, c.comment, c.blank, c.total, c.doc
+        );
+    };
+    for p in points {
+        for (lang, c) in &p.languages {
+            push(p, lang, c);
+        }
+        push(p, "Total", &p.totals);
+    }
+    out
+}
+
+fn format_table(points: &[HistoryPoint]) -> String {
+    let mut out = format!(
+        "{:<20} {:<10} {:>7} {:>10} {:>10} {:>10} {:>10} {:>10}\n",
+        "date", "commit", "files", "code", "comment", "doc", "blank", "total"
+    );
+    let _ = writeln!(out, "{}", "-".repeat(20 + 10 + 7 + 10 * 5 + 7));
+    for p in points {
+        let t = &p.totals;
+        let _ = writeln!(
+            out,
+            "{:<20} {:<10} {:>7} {:>10} {:>10} {:>10} {:>10} {:>10}",
+            p.date,
+            &p.commit[..p.commit.len().min(10)],
+            t.files,
+            t.code,
+            t.comment,
+            t.doc,
+            t.blank,
+            t.total
+        );
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn samples_by_commit_count_and_by_days() {
+        const DAY: i64 = 86_400;
+        let commits: Vec<(Oid, i64)> = [0, 1, 2, 3, 10, 11, 12]
+            .iter()
+            .map(|d| (Oid::zero(), d * DAY))
+            .collect();
+        let days = |s: Vec<(Oid, i64)>| s.iter().map(|&(_, t)| t / DAY).collect::<Vec<_>>();
+        assert_eq!(days(sample(&commits, None, None)), [0, 1, 2, 3, 10, 11, 12]);
+        assert_eq!(days(sample(&commits, Some(3), None)), [0, 3, 12]);
+        assert_eq!(days(sample(&commits, None, Some(2))), [0, 2, 10, 12]);
+        assert_eq!(days(sample(&commits, None, Some(30))), [0, 12]);
+        assert!(sample(&[], Some(2), None).is_empty());
+    }
+}
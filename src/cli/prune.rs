@@ -0,0 +1,19 @@
+use anyhow::Result;
+
+use crate::cache;
+use crate::vcs::VcsContext;
+
+use super::PruneArgs;
+
+/// `ocloc cache prune`: remove stale blob cache files and entries.
+pub fn run_prune(args: &PruneArgs) -> Result<()> {
+    let vcs = VcsContext::open(&args.path)?;
+    let stats = cache::prune(&vcs.repo, args.all)?;
+    println!(
+        "Removed {} cache file(s) and {} stale entr{}",
+        stats.files,
+        stats.entries,
+        if stats.entries == 1 { "y" } else { "ies" }
+    );
+    Ok(())
+}
@@ -0,0 +1,86 @@
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use anyhow::{Context, Result};
+use git2::Repository;
+
+use crate::codeowners::{CODEOWNERS_LOCATIONS, CodeOwners};
+use crate::vcs::VcsContext;
+
+use super::run_impl::{init_thread_pool, print_result};
+use super::tally::TallyOptions;
+use super::tree::TreeCount;
+use super::{Args, open_cache};
+
+/// `--rev`: count the files in the tree of a revision, reading blobs from the object
+/// database. Works on bare repositories; in a work tree, PATH selects a subdirectory.
+pub fn run_rev(args: Args) -> Result<()> {
+    let start_time = Instant::now();
+    init_thread_pool(args.threads);
+    let rev = args.rev.as_deref().unwrap_or("HEAD");
+
+    let vcs = VcsContext::open(&args.path)?;
+    let prefix = tree_prefix(&vcs.repo, &args.path)?;
+    let files = vcs.tree_files(rev, &prefix)?;
+    if args.verbose > 0 {
+        eprintln!("Scanning {} files at {rev}", files.len());
+    }
+
+    // CODEOWNERS comes from the root of the revision's tree, whatever PATH selects
+    let codeowners = if args.by_owner {
+        let text = CODEOWNERS_LOCATIONS
+            .iter()
+            .find_map(|location| vcs.read_tree_file(rev, Path::new(location)));
+        let text = text.with_context(|| format!("--by-owner: no CODEOWNERS file in {rev}"))?;
+        Some(CodeOwners::parse(Path::new(""), &String::from_utf8_lossy(&text)).within(&prefix))
+    } else {
+        None
+    };
+
+    let analyzer = args.analyzer()?;
+    let allowed_exts = args.allowed_exts();
+    let tally_opts = TallyOptions::new(&args, codeowners.as_ref());
+    let cache = open_cache(&vcs.repo, &analyzer, args.no_cache);
+    let counter = TreeCount {
+        analyzer: &analyzer,
+        cache: &cache,
+        opts: &tally_opts,
+        allowed_exts: allowed_exts.as_ref(),
+        min_size: args.min_size,
+        max_size: args.max_size,
+        seen: None,
+    };
+    let tally = counter.count(&vcs, rev, &prefix, &files)?;
+    cache.save()?;
+
+    let repo_name = vcs
+        .repo
+        .workdir()
+        .unwrap_or(vcs.repo.path())
+        .join(&prefix)
+        .display()
+        .to_string();
+    let analyze = tally.finish(
+        &tally_opts,
+        files.len(),
+        start_time.elapsed().as_secs_f64(),
+        format!("{}@{rev}", repo_name.trim_end_matches('/')),
+    );
+    print_result(&args, &analyze)
+}
+
+/// Directory of `path` inside the repository's work tree, as a tree path; empty for bare
+/// repositories and the work tree root.
+fn tree_prefix(repo: &Repository, path: &Path) -> Result<PathBuf> {
+    let Some(workdir) = repo.workdir() else {
+        return Ok(PathBuf::new());
+    };
+    let path = path
+        .canonicalize()
+        .with_context(|| format!("resolve {}", path.display()))?;
+    let workdir = workdir.canonicalize().context("resolve work tree")?;
+    Ok(path
+        .strip_prefix(&workdir)
+        .map(Path::to_path_buf)
+        .unwrap_or_default())
+}
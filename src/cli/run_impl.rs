@@ -1,33 +1,26 @@
-use anyhow::Result;
+use anyhow::{Context, Result, bail};
 // use rayon::prelude::*; // not used after switching to WalkParallel
-use std::collections::HashSet;
 use std::fs;
-use std::sync::Arc;
 use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
 use std::time::Instant;
 
-use crate::languages::find_language_for_path;
+use crate::cache::{FileStamp, ScanCache, ScanEntry, default_scan_cache_path};
+use crate::codeowners::CodeOwners;
+use crate::formatters;
+use crate::languages::{Detection, DetectionSource};
 use crate::traversal::{TraversalOptions, build_walk_builder};
-use crate::types::{AnalyzeResult, FileCounts, FileStats};
-use crate::{analyzer, formatters};
+use crate::types::{AnalyzeResult, FileAnalysis};
+use crate::vcs::LinguistAttributes;
 
 use super::Args;
+use super::tally::{FileRecord, Tally, TallyOptions};
 
 pub fn run_with_args(args: Args) -> Result<()> {
     let start_time = Instant::now();
-    if args.threads > 0 {
-        rayon::ThreadPoolBuilder::new()
-            .num_threads(args.threads)
-            .build_global()
-            .ok();
-    }
+    init_thread_pool(args.threads);
 
-    let allowed_exts: Option<HashSet<String>> = args.extensions.as_ref().map(|s| {
-        s.split(',')
-            .filter(|t| !t.trim().is_empty())
-            .map(|t| t.trim().trim_start_matches('.').to_ascii_lowercase())
-            .collect()
-    });
+    let allowed_exts = args.allowed_exts();
 
     let opts = TraversalOptions {
         follow_symlinks: args.follow_symlinks,
@@ -43,8 +36,8 @@ pub fn run_with_args(args: Args) -> Result<()> {
             eprintln!("Extensions filter: {}", list);
         }
     }
-    // Configure analyzer global settings (no-mmap and threshold)
-    analyzer::set_analyzer_config(args.no_mmap, args.mmap_large);
+    // Analyzer settings (no-mmap, threshold and docstring policy)
+    let analyzer = args.analyzer()?;
     // Build a parallel walker; we'll analyze as we traverse
     let walker = build_walk_builder(&args.path, &opts).build_parallel();
 
@@ -64,45 +57,60 @@ pub fn run_with_args(args: Args) -> Result<()> {
     };
 
     // Switch to direct parallel analysis over files, with batched progress updates
-    let ignored_counter = Arc::new(AtomicUsize::new(0));
-    let empty_counter = Arc::new(AtomicUsize::new(0));
     let progress_counter = Arc::new(AtomicUsize::new(0));
-    let global_map: Arc<std::sync::Mutex<indexmap::IndexMap<String, FileCounts>>> =
-        Arc::new(std::sync::Mutex::new(indexmap::IndexMap::new()));
+    let global = Arc::new(Mutex::new(Tally::default()));
 
-    struct ThreadAgg {
-        local: indexmap::IndexMap<String, FileCounts>,
-        global: Arc<std::sync::Mutex<indexmap::IndexMap<String, FileCounts>>>,
+    // Each walker thread tallies on its own and merges when it finishes
+    struct ThreadTally {
+        local: Tally,
+        global: Arc<Mutex<Tally>>,
     }
-    impl ThreadAgg {
-        fn new(global: Arc<std::sync::Mutex<indexmap::IndexMap<String, FileCounts>>>) -> Self {
-            Self {
-                local: indexmap::IndexMap::new(),
-                global,
-            }
-        }
-        fn add(&mut self, lang: String, counts: FileCounts) {
-            let entry = self.local.entry(lang).or_default();
-            entry.merge(&counts);
-        }
-    }
-    impl Drop for ThreadAgg {
+    impl Drop for ThreadTally {
         fn drop(&mut self) {
             if let Ok(mut g) = self.global.lock() {
-                for (lang, counts) in self.local.drain(..) {
-                    let e = g.entry(lang).or_default();
-                    e.merge(&counts);
-                }
+                g.merge(std::mem::take(&mut self.local));
             }
         }
     }
 
+    // `--by-file` paths are shown relative to the scanned directory
+    let root = args.path.as_path();
+    // CODEOWNERS comes from the work tree root; its patterns are matched from there
+    let codeowners = if args.by_owner {
+        match CodeOwners::discover(root)? {
+            Some(c) => Some(c),
+            None => bail!(
+                "--by-owner: no CODEOWNERS file for {} (looked in ./, .github/ and docs/ of \
+                 the work tree root)",
+                root.display()
+            ),
+        }
+    } else {
+        None
+    };
+    let tally_opts = TallyOptions::new(&args, codeowners.as_ref());
+    let scan_cache = match &args.scan_cache {
+        Some(file) => {
+            let file = file
+                .clone()
+                .or_else(|| default_scan_cache_path(root))
+                .context("--scan-cache: set XDG_CACHE_HOME or HOME, or pass a cache file")?;
+            Some(ScanCache::open(file, &analyzer))
+        }
+        None => None,
+    };
     walker.run(|| {
-        let ignored_counter = ignored_counter.clone();
-        let empty_counter = empty_counter.clone();
+        let scan_cache = scan_cache.as_ref();
         let progress_counter = progress_counter.clone();
-        let mut agg = ThreadAgg::new(global_map.clone());
+        // `.gitattributes` lookups need a repository handle per thread
+        let attributes = LinguistAttributes::open(&args.path);
+        let mut agg = ThreadTally {
+            local: Tally::default(),
+            global: global.clone(),
+        };
         let pb_inner = pb.as_ref().cloned();
+        let analyzer = &analyzer;
+        let tally_opts = &tally_opts;
         Box::new(move |entry: Result<ignore::DirEntry, ignore::Error>| {
             let dent: ignore::DirEntry = match entry {
                 Ok(d) => d,
@@ -112,6 +120,7 @@ pub fn run_with_args(args: Args) -> Result<()> {
             if !path.is_file() {
                 return ignore::WalkState::Continue;
             }
+            let tally = &mut agg.local;
 
             // Count every visited file for stats/progress
             let n = progress_counter.fetch_add(1, Ordering::Relaxed) + 1;
@@ -121,133 +130,194 @@ pub fn run_with_args(args: Args) -> Result<()> {
                 }
             }
 
-            // Detect language (may read shebang)
-            let lang_opt = find_language_for_path(path);
-            if lang_opt.is_none() {
-                ignored_counter.fetch_add(1, Ordering::Relaxed);
+            let rel = match path.strip_prefix(root) {
+                Ok(rel) if !rel.as_os_str().is_empty() => rel,
+                _ => path,
+            };
+            let attrs = attributes
+                .as_ref()
+                .map(|a| a.lookup(path))
+                .unwrap_or_default();
+
+            // With --scan-cache, an unchanged file reuses its language and counts
+            let stamp = scan_cache
+                .and_then(|_| dent.metadata().ok())
+                .map(|md| FileStamp::of(&md));
+            let cache_key = rel.to_string_lossy();
+            let cached = scan_cache.zip(stamp).and_then(|(cache, stamp)| {
+                cache.lookup(&cache_key, stamp, attrs.language.as_deref())
+            });
+            // Detect language: a `linguist-language` override, else filename, extension
+            // and content (may read the start of the file)
+            let registry = analyzer.registry();
+            let detection = match &cached {
+                Some(entry) => entry.detection,
+                None => attrs
+                    .language
+                    .as_deref()
+                    .and_then(|name| registry.find_index_by_name(name))
+                    .map(|index| Detection {
+                        index,
+                        source: DetectionSource::Attribute,
+                    })
+                    .or_else(|| registry.detect(path, None)),
+            };
+            let remember = |detection, analysis| {
+                if let (Some(cache), Some(stamp)) = (scan_cache, stamp) {
+                    let entry = ScanEntry {
+                        stamp,
+                        attr_language: attrs.language.clone(),
+                        detection,
+                        analysis,
+                    };
+                    cache.store(&cache_key, entry);
+                }
+            };
+            if cached.is_none() {
+                remember(detection, None);
+            }
+            let Some(detection) = detection else {
+                tally.ignored_files += 1;
                 return ignore::WalkState::Continue;
+            };
+
+            // Vendored files are skipped unread; generated ones are analyzed for their
+            // own section
+            if attrs.vendored && !attrs.generated {
+                tally.vendored_files += 1;
+                if !args.include_generated {
+                    return ignore::WalkState::Continue;
+                }
             }
-            let lang = lang_opt.unwrap().to_string();
 
             // Guard metadata calls: only when filters require it
             if opts.min_size.is_some() || opts.max_size.is_some() || args.skip_empty {
-                if let Ok(md) = fs::metadata(path) {
+                let size = match stamp {
+                    Some(stamp) => Some(stamp.size),
+                    None => fs::metadata(path).ok().map(|md| md.len()),
+                };
+                if let Some(size) = size {
                     // Apply min/max size filters if provided
                     if let Some(min) = opts.min_size {
-                        if md.len() < min {
+                        if size < min {
                             return ignore::WalkState::Continue;
                         }
                     }
                     if let Some(max) = opts.max_size {
-                        if md.len() > max {
+                        if size > max {
                             return ignore::WalkState::Continue;
                         }
                     }
-                    if args.skip_empty && md.len() == 0 {
-                        empty_counter.fetch_add(1, Ordering::Relaxed);
+                    if args.skip_empty && size == 0 {
+                        tally.empty_files += 1;
                         return ignore::WalkState::Continue;
                     }
                 }
             }
 
-            let counts = analyzer::analyze_file(path).unwrap_or_else(|_| FileCounts::default());
-            if counts.files > 0 {
-                if args.ultra {
-                    // In ultra mode, avoid per-language aggregation; accumulate totals only
-                    let total_only = agg.local.entry("__TOTAL__".to_string()).or_default();
-                    total_only.merge(&counts);
-                } else {
-                    agg.add(lang, counts);
+            // Hits are cached counts actually used, misses files analyzed with the cache on
+            let analysis = match cached.and_then(|entry| entry.analysis) {
+                Some(analysis) => {
+                    tally.cache_hits += 1;
+                    analysis
                 }
-            }
+                None => {
+                    if stamp.is_some() {
+                        tally.cache_misses += 1;
+                    }
+                    // A failed read counts as empty this time but is not remembered, so the
+                    // file is read again once it is readable (permission changes keep the stamp)
+                    match analyzer.analyze_file_regions_as(path, detection.index) {
+                        Ok(analysis) => {
+                            remember(Some(detection), Some(analysis.clone()));
+                            analysis
+                        }
+                        Err(_) => FileAnalysis::default(),
+                    }
+                }
+            };
+            let file = FileRecord {
+                path: rel,
+                language: registry[detection.index].name.clone(),
+                detected_by: Some(detection.source),
+                bytes: if let Some(stamp) = stamp {
+                    stamp.size
+                } else if args.by_file {
+                    dent.metadata().map(|m| m.len()).unwrap_or(0)
+                } else {
+                    0
+                },
+                marked_generated: attrs.generated,
+            };
+            tally.record(tally_opts, file, analysis);
             ignore::WalkState::Continue
         })
     });
 
-    let per_lang_map = Arc::try_unwrap(global_map).unwrap().into_inner().unwrap();
-
-    let mut per_lang: indexmap::IndexMap<String, FileCounts> = indexmap::IndexMap::new();
-    let mut totals = FileCounts::default();
-    if args.ultra {
-        if let Some(total_counts) = per_lang_map.get("__TOTAL__") {
-            totals = *total_counts;
-        }
-    } else {
-        for (lang, counts) in per_lang_map.iter() {
-            per_lang.insert(lang.clone(), *counts);
-            totals.merge(counts);
+    let tally = Arc::try_unwrap(global).unwrap().into_inner().unwrap();
+    if let Some(cache) = scan_cache {
+        if args.verbose > 0 {
+            eprintln!(
+                "Scan cache: {} hits, {} misses",
+                tally.cache_hits, tally.cache_misses
+            );
         }
+        cache.save()?;
     }
-
-    // Sort per_lang by descending code (then total) before serializing/printing
-    let mut per_lang: Vec<(String, FileCounts)> = per_lang.into_iter().collect();
-    per_lang.sort_by(|a, b| {
-        b.1.code
-            .cmp(&a.1.code)
-            .then_with(|| b.1.total.cmp(&a.1.total))
-            .then_with(|| a.0.cmp(&b.0))
-    });
-    let per_lang: indexmap::IndexMap<String, FileCounts> = per_lang.into_iter().collect();
-
-    let elapsed = start_time.elapsed().as_secs_f64();
-
-    let stats = FileStats {
-        total_files: progress_counter.load(Ordering::Relaxed),
-        unique_files: if args.ultra {
-            totals.files
-        } else {
-            per_lang_map.values().map(|c| c.files).sum::<usize>()
-        },
-        ignored_files: ignored_counter.load(Ordering::Relaxed),
-        empty_files: if args.skip_empty {
-            0
-        } else {
-            empty_counter.load(Ordering::Relaxed)
-        },
-        elapsed_seconds: elapsed,
-    };
-
-    let analyze = AnalyzeResult {
-        per_lang,
-        totals,
-        files_analyzed: totals.files,
-        stats: Some(stats),
-        analyzed_path: Some(
-            args.path
-                .canonicalize()
-                .unwrap_or(args.path.clone())
-                .display()
-                .to_string(),
-        ),
-    };
+    let analyze = tally.finish(
+        &tally_opts,
+        progress_counter.load(Ordering::Relaxed),
+        start_time.elapsed().as_secs_f64(),
+        args.path
+            .canonicalize()
+            .unwrap_or(args.path.clone())
+            .display()
+            .to_string(),
+    );
 
     if let Some(pb) = pb {
         pb.finish_and_clear();
     }
+    print_result(&args, &analyze)
+}
+
+/// Size the global rayon pool (`--threads`; 0 keeps the default).
+pub(super) fn init_thread_pool(threads: usize) {
+    if threads > 0 {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build_global()
+            .ok();
+    }
+}
+
+/// Print `analyze` as JSON, CSV or the default table, as selected by `args`.
+pub(super) fn print_result(args: &Args, analyze: &AnalyzeResult) -> Result<()> {
     if args.verbose > 1 {
         eprintln!(
-            "Totals: files={}, code={}, comment={}, blank={}, total={}",
+            "Totals: files={}, code={}, comment={}, doc={}, blank={}, total={}",
             analyze.totals.files,
             analyze.totals.code,
             analyze.totals.comment,
+            analyze.totals.doc,
             analyze.totals.blank,
             analyze.totals.total
         );
     }
 
     if args.json {
-        let s = serde_json::to_string_pretty(&analyze)?;
+        let s = serde_json::to_string_pretty(analyze)?;
         println!("{}", s);
         return Ok(());
     }
     if args.csv {
-        let s = formatters::csv::format(&analyze);
+        let s = formatters::csv::format(analyze);
         println!("{}", s);
         return Ok(());
     }
 
     // default pretty table (ultra still prints table, but with only totals)
-    let s = formatters::table::format(&analyze);
+    let s = formatters::table::format(analyze);
     println!("{}", s);
     Ok(())
 }
@@ -1,13 +1,13 @@
-use anyhow::{Context, Result, bail};
+use anyhow::{Context, Result, anyhow, bail};
+use rayon::prelude::*;
 use std::collections::HashSet;
-use std::io::Cursor;
-use std::path::Path;
+use std::fs;
+use std::path::{Path, PathBuf};
 
-use crate::analyzer;
-use crate::languages::find_language_for_path;
+use crate::analyzer::{Analyzer, LineKind, decode_text};
 use crate::types::FileCounts;
-use crate::types_diff::{DiffPerFile, DiffSummary, GitRefInfo, LineDelta};
-use crate::vcs::VcsContext;
+use crate::types_diff::{DiffPerFile, DiffSummary, GitRefInfo, LineChurn, LineDelta};
+use crate::vcs::{VcsContext, changed_lines};
 
 use super::DiffArgs;
 
@@ -101,115 +101,148 @@ pub fn run_diff(args: &DiffArgs) -> Result<()> {
         }
     };
 
+    let analyzer = Analyzer::builder()
+        .registry(super::load_registry(&args.lang_files)?)
+        .build();
+
+    let cache = super::open_cache(&vcs.repo, &analyzer, args.no_cache);
+
     // Optional extension filter
-    let allowed_exts: Option<HashSet<String>> = args.extensions.as_ref().map(|s| {
-        s.split(',')
-            .filter(|t| !t.trim().is_empty())
-            .map(|t| t.trim().trim_start_matches('.').to_ascii_lowercase())
-            .collect()
-    });
+    let allowed_exts: Option<HashSet<String>> =
+        args.extensions.as_deref().map(super::parse_extensions);
 
     // Process changes in parallel
     let mut per_file: Vec<DiffPerFile> = Vec::new();
     let mut per_lang: indexmap::IndexMap<String, LineDelta> = indexmap::IndexMap::new();
 
+    // git2 repositories are not shared between threads: each worker opens its own handle.
+    // Results are collected in diff order, so the output does not depend on scheduling
+    let repo_path = vcs.repo.path();
     let items: Vec<_> = changes
-        .into_iter()
-        .filter_map(|c| {
-            let path_for_lang = c.new_path.as_ref().or(c.old_path.as_ref()).cloned();
-            let path_hint = path_for_lang?;
-
-            if let Some(ref allowed) = allowed_exts {
-                if let Some(ext) = path_hint.extension().and_then(|s| s.to_str()) {
-                    if !allowed.contains(&ext.to_ascii_lowercase()) {
-                        return None;
+        .into_par_iter()
+        .map_init(
+            || VcsContext::open(repo_path),
+            |vcs, c| -> Result<Option<ChangedFile>> {
+                let vcs = vcs
+                    .as_ref()
+                    .map_err(|e| anyhow!("open git repo for worker: {e:#}"))?;
+                let path_for_lang = c.new_path.as_ref().or(c.old_path.as_ref()).cloned();
+                let Some(path_hint) = path_for_lang else {
+                    return Ok(None);
+                };
+
+                if let Some(ref allowed) = allowed_exts {
+                    if let Some(ext) = path_hint.extension().and_then(|s| s.to_str()) {
+                        if !allowed.contains(&ext.to_ascii_lowercase()) {
+                            return Ok(None);
+                        }
+                    } else {
+                        return Ok(None);
                     }
-                } else {
-                    return None;
                 }
-            }
 
-            let lang = find_language_for_path(&path_hint).unwrap_or("Unknown");
+                // Blobs go through the cache; worktree and index content is analyzed directly.
+                // The head blob decides the language, the worktree file otherwise
+                let head = c
+                    .oids
+                    .new
+                    .and_then(|oid| cache.analyze_blob(&analyzer, &vcs.repo, &path_hint, oid));
+                let registry = analyzer.registry();
+                let lang = match &head {
+                    Some(blob) => blob.language.clone(),
+                    None => registry
+                        .detect(&path_hint, None)
+                        .map(|d| registry[d.index].name.clone()),
+                };
 
-            // Analyze base and head content with sensible fallbacks
-            let base_counts = if let Some(bytes) = vcs.read_blob_bytes(c.oids.old) {
-                analyze_bytes(&bytes, &path_hint).unwrap_or_default()
-            } else if let Some(ref p) = c.old_path {
-                if let Some(bytes) = vcs.read_index_blob_bytes(p) {
-                    analyze_bytes(&bytes, &path_hint).unwrap_or_default()
+                // Analyze base and head content with sensible fallbacks
+                let base = c
+                    .oids
+                    .old
+                    .and_then(|oid| cache.analyze_blob(&analyzer, &vcs.repo, &path_hint, oid));
+                let base_counts = if let Some(blob) = base {
+                    blob.analysis.combined()
+                } else if let Some(ref p) = c.old_path {
+                    if let Some(bytes) = vcs.read_index_blob_bytes(p) {
+                        analyzer
+                            .analyze_bytes(&bytes, &path_hint)
+                            .unwrap_or_default()
+                    } else {
+                        analyzer.analyze_file(p).unwrap_or_default()
+                    }
                 } else {
-                    analyzer::analyze_file(p).unwrap_or_default()
-                }
-            } else {
-                FileCounts::default()
-            };
-            let head_counts = if let Some(bytes) = vcs.read_blob_bytes(c.oids.new) {
-                analyze_bytes(&bytes, &path_hint).unwrap_or_default()
-            } else if let Some(ref p) = c.new_path {
-                analyzer::analyze_file(p).unwrap_or_default()
-            } else {
-                FileCounts::default()
-            };
+                    FileCounts::default()
+                };
+                let head_counts = if let Some(blob) = head {
+                    blob.analysis.combined()
+                } else if let Some(ref p) = c.new_path {
+                    analyzer.analyze_file(p).unwrap_or_default()
+                } else {
+                    FileCounts::default()
+                };
 
-            let code_delta = head_counts.code as isize - base_counts.code as isize;
-            let comment_delta = head_counts.comment as isize - base_counts.comment as isize;
-            let blank_delta = head_counts.blank as isize - base_counts.blank as isize;
-            let total_delta = head_counts.total as isize - base_counts.total as isize;
-
-            let status = c.status.clone();
-            let lang = lang.to_string();
-            Some((
-                path_hint,
-                status,
-                lang,
-                base_counts,
-                head_counts,
-                code_delta,
-                comment_delta,
-                blank_delta,
-                total_delta,
-            ))
-        })
+                // --hunks: classify the lines of both sides and pick out the changed ones
+                let churn = if args.hunks {
+                    let base_bytes = vcs
+                        .read_blob_bytes(c.oids.old)
+                        .or_else(|| {
+                            let p = c.old_path.as_ref()?;
+                            vcs.read_index_blob_bytes(p).or_else(|| fs::read(p).ok())
+                        })
+                        .unwrap_or_default();
+                    let head_bytes = vcs
+                        .read_blob_bytes(c.oids.new)
+                        .or_else(|| fs::read(c.new_path.as_ref()?).ok())
+                        .unwrap_or_default();
+                    Some(hunk_churn(&analyzer, &path_hint, &base_bytes, &head_bytes)?)
+                } else {
+                    None
+                };
+
+                Ok(Some(ChangedFile {
+                    path: path_hint,
+                    status: c.status.clone(),
+                    language: lang.unwrap_or_else(|| "Unknown".to_string()),
+                    base: base_counts,
+                    head: head_counts,
+                    churn,
+                }))
+            },
+        )
+        .collect::<Result<Vec<_>>>()?
+        .into_iter()
+        .flatten()
         .collect();
 
-    for (
-        path_hint,
-        status,
-        lang,
-        base_counts,
-        head_counts,
-        code_delta,
-        comment_delta,
-        blank_delta,
-        total_delta,
-    ) in items
-    {
+    for file in items {
         per_file.push(DiffPerFile {
-            path: path_hint.display().to_string(),
-            status,
-            language: lang.clone(),
-            code_delta,
-            comment_delta,
-            blank_delta,
-            total_delta,
+            path: file.path.display().to_string(),
+            status: file.status,
+            language: file.language.clone(),
+            code_delta: file.head.code as isize - file.base.code as isize,
+            comment_delta: file.head.comment as isize - file.base.comment as isize,
+            blank_delta: file.head.blank as isize - file.base.blank as isize,
+            doc_delta: file.head.doc as isize - file.base.doc as isize,
+            total_delta: file.head.total as isize - file.base.total as isize,
+            churn: file.churn,
         });
 
-        let entry = per_lang.entry(lang.clone()).or_default();
-        entry.add_file_delta(
-            (base_counts.code, base_counts.comment, base_counts.blank),
-            (head_counts.code, head_counts.comment, head_counts.blank),
-        );
+        let entry = per_lang.entry(file.language).or_default();
+        match file.churn {
+            Some(churn) => entry.add_file_churn(&file.base, &file.head, &churn),
+            None => entry.add_file_delta(&file.base, &file.head),
+        }
     }
 
+    cache.save()?;
+
     // Totals
-    let mut totals = LineDelta::default();
-    for (_lang, d) in per_lang.iter() {
-        totals.files += d.files;
-        totals.code_added += d.code_added;
-        totals.code_removed += d.code_removed;
-        totals.comment_added += d.comment_added;
-        totals.blank_added += d.blank_added;
-        totals.total_net += d.total_net;
+    let mut totals = LineDelta {
+        churn: args.hunks.then(LineChurn::default),
+        ..LineDelta::default()
+    };
+    for d in per_lang.values() {
+        totals.merge(d);
     }
 
     let summary = DiffSummary {
@@ -222,6 +255,7 @@ pub fn run_diff(args: &DiffArgs) -> Result<()> {
         files_deleted: per_file.iter().filter(|f| f.status == "D").count(),
         files_modified: per_file.iter().filter(|f| f.status == "M").count(),
         files_renamed: per_file.iter().filter(|f| f.status == "R").count(),
+        hunks: args.hunks,
         languages: per_lang,
         by_file: if args.by_file && !args.summary_only {
             per_file
@@ -235,11 +269,14 @@ pub fn run_diff(args: &DiffArgs) -> Result<()> {
     // Threshold checks: only fail with non-zero exit if explicitly requested
     let mut threshold_errors: Vec<String> = Vec::new();
     if let Some(max) = args.max_code_added {
-        if summary.totals.code_added > max as isize {
-            threshold_errors.push(format!(
-                "code delta {} exceeds threshold {}",
-                summary.totals.code_added, max
-            ));
+        let added = code_added(summary.totals.code_added, summary.totals.churn.as_ref());
+        if added > max as isize {
+            let what = if args.hunks {
+                "code added"
+            } else {
+                "code delta"
+            };
+            threshold_errors.push(format!("{what} {added} exceeds threshold {max}"));
         }
     }
     if let Some(max) = args.max_total_changed {
@@ -270,7 +307,7 @@ pub fn run_diff(args: &DiffArgs) -> Result<()> {
         let mut violations = Vec::new();
         for (lang, d) in &summary.languages {
             if let Some(limit) = limits.get(lang) {
-                if d.code_added > *limit {
+                if code_added(d.code_added, d.churn.as_ref()) > *limit {
                     violations.push(format!("{}>{}", lang, limit));
                 }
             }
@@ -296,80 +333,166 @@ pub fn run_diff(args: &DiffArgs) -> Result<()> {
     Ok(())
 }
 
-fn analyze_bytes(bytes: &[u8], path_hint: &Path) -> Result<FileCounts> {
-    let cursor = Cursor::new(bytes);
-    let reader = std::io::BufReader::new(cursor);
-    analyzer::analyze_reader_owned(reader, path_hint)
+/// Counts of one changed file on both sides, as computed by a diff worker.
+struct ChangedFile {
+    path: PathBuf,
+    status: String,
+    language: String,
+    base: FileCounts,
+    head: FileCounts,
+    /// Added and removed lines by kind, with `--hunks`
+    churn: Option<LineChurn>,
+}
+
+/// Code lines added, as gated by `--max-code-added`: the lines added in the diff hunks
+/// with `--hunks`, the net code delta otherwise.
+fn code_added(net: isize, churn: Option<&LineChurn>) -> isize {
+    churn.map_or(net, |c| c.code_added as isize)
+}
+
+/// Added and removed lines of a change, classified with the analyzer on their own side so
+/// that comment state (e.g. inside a block comment) is taken from the whole file. Both
+/// sides are diffed as the classifier reads them (UTF-16 transcoded, without a BOM); a
+/// binary side has no lines.
+fn hunk_churn(analyzer: &Analyzer, path: &Path, base: &[u8], head: &[u8]) -> Result<LineChurn> {
+    let base = decode_text(base).unwrap_or_default();
+    let head = decode_text(head).unwrap_or_default();
+    let (removed, added) = changed_lines(&base, &head)?;
+    let mut churn = LineChurn::default();
+    for (bytes, lines, is_added) in [(&base, removed, false), (&head, added, true)] {
+        if lines.is_empty() {
+            continue;
+        }
+        let mut kinds = Vec::new();
+        analyzer.classify_lines(&mut &bytes[..], path, |n, kind| {
+            if kinds.len() < n {
+                kinds.resize(n, LineKind::Blank);
+            }
+            kinds[n - 1] = kind;
+        })?;
+        for n in lines {
+            let kind = kinds.get(n - 1).copied().unwrap_or(LineKind::Blank);
+            churn.add_line(kind, is_added);
+        }
+    }
+    Ok(churn)
 }
 
 fn print_table(s: &DiffSummary) {
-    // Simple table: Language, files, codeΔ, commentΔ, blankΔ, totalΔ
+    // Simple table: Language, files, codeΔ, commentΔ, docΔ, blankΔ, totalΔ; with --hunks
+    // each category shows lines added/removed
+    let w = if s.hunks { 16 } else { 10 };
     println!(
-        "{:<20} {:>7} {:>10} {:>10} {:>10} {:>10}",
-        "Language", "files", "code", "comment", "blank", "net"
+        "{:<20} {:>7} {:>w$} {:>w$} {:>w$} {:>w$} {:>10}",
+        "Language", "files", "code", "comment", "doc", "blank", "net"
     );
-    println!(
-        "{}",
-        "-".repeat(20 + 1 + 7 + 1 + 10 + 1 + 10 + 1 + 10 + 1 + 10)
-    );
-    for (lang, d) in &s.languages {
+    let rule = "-".repeat(20 + 1 + 7 + (1 + w) * 4 + 1 + 10);
+    println!("{}", rule);
+    let row = |name: &str, d: &LineDelta| {
+        let [code, comment, doc, blank] = category_cells(
+            d.churn.as_ref(),
+            [d.code_added, d.comment_added, d.doc_added, d.blank_added],
+            |n| format!("{n:+}"),
+        );
         println!(
-            "{:<20} {:>7} {:>+10} {:>+10} {:>+10} {:>+10}",
-            lang, d.files, d.code_added, d.comment_added, d.blank_added, d.total_net
+            "{:<20} {:>7} {:>w$} {:>w$} {:>w$} {:>w$} {:>+10}",
+            name, d.files, code, comment, doc, blank, d.total_net
         );
+    };
+    for (lang, d) in &s.languages {
+        row(lang, d);
     }
-    println!(
-        "{}",
-        "-".repeat(20 + 1 + 7 + 1 + 10 + 1 + 10 + 1 + 10 + 1 + 10)
-    );
-    println!(
-        "{:<20} {:>7} {:>+10} {:>+10} {:>+10} {:>+10}",
-        "Total",
-        s.totals.files,
-        s.totals.code_added,
-        s.totals.comment_added,
-        s.totals.blank_added,
-        s.totals.total_net
-    );
+    println!("{}", rule);
+    row("Total", &s.totals);
+}
+
+/// Code, comment, doc and blank cells: `+added/-removed` from the diff hunks when there is
+/// churn (`--hunks`), the `net` changes formatted by `net_cell` otherwise.
+fn category_cells(
+    churn: Option<&LineChurn>,
+    net: [isize; 4],
+    net_cell: impl Fn(isize) -> String,
+) -> [String; 4] {
+    match churn {
+        Some(c) => [
+            (c.code_added, c.code_removed),
+            (c.comment_added, c.comment_removed),
+            (c.doc_added, c.doc_removed),
+            (c.blank_added, c.blank_removed),
+        ]
+        .map(|(added, removed)| format!("+{added}/-{removed}")),
+        None => net.map(net_cell),
+    }
+}
+
+/// Extra CSV columns for `--hunks`, in `CHURN_CSV_HEADER` order.
+fn churn_csv(churn: Option<&LineChurn>) -> String {
+    let c = churn.copied().unwrap_or_default();
+    format!(
+        ",{},{},{},{},{},{},{},{}",
+        c.code_added,
+        c.code_removed,
+        c.comment_added,
+        c.comment_removed,
+        c.blank_added,
+        c.blank_removed,
+        c.doc_added,
+        c.doc_removed
+    )
 }
 
+const CHURN_CSV_HEADER: &str = ",hunk_code_added,hunk_code_removed,hunk_comment_added,hunk_comment_removed,hunk_blank_added,hunk_blank_removed,hunk_doc_added,hunk_doc_removed";
+
 fn print_csv(s: &DiffSummary) {
-    println!("language,files,code_added,code_removed,comment_added,blank_added,net_delta");
-    for (lang, d) in &s.languages {
+    // New columns are appended so existing column positions stay stable
+    let churn_header = if s.hunks { CHURN_CSV_HEADER } else { "" };
+    println!(
+        "language,files,code_added,code_removed,comment_added,blank_added,net_delta,doc_added{churn_header}"
+    );
+    let row = |name: &str, d: &LineDelta| {
         println!(
-            "{},{},{},{},{},{},{}",
-            lang,
+            "{},{},{},{},{},{},{},{}{}",
+            name,
             d.files,
             d.code_added,
             d.code_removed,
             d.comment_added,
             d.blank_added,
-            d.total_net
+            d.total_net,
+            d.doc_added,
+            if s.hunks {
+                churn_csv(d.churn.as_ref())
+            } else {
+                String::new()
+            }
         );
+    };
+    for (lang, d) in &s.languages {
+        row(lang, d);
     }
-    println!(
-        "Total,{},{},{},{},{},{}",
-        s.totals.files,
-        s.totals.code_added,
-        s.totals.code_removed,
-        s.totals.comment_added,
-        s.totals.blank_added,
-        s.totals.total_net
-    );
+    row("Total", &s.totals);
 
     if !s.by_file.is_empty() {
         println!();
-        println!("path,status,language,code_delta,comment_delta,blank_delta,net_delta");
+        println!(
+            "path,status,language,code_delta,comment_delta,blank_delta,net_delta,doc_delta{churn_header}"
+        );
         for f in &s.by_file {
             println!(
-                "{},{},{},{},{},{},{}",
+                "{},{},{},{},{},{},{},{}{}",
                 f.path,
                 f.status,
                 f.language,
                 f.code_delta,
                 f.comment_delta,
                 f.blank_delta,
-                f.total_delta
+                f.total_delta,
+                f.doc_delta,
+                if s.hunks {
+                    churn_csv(f.churn.as_ref())
+                } else {
+                    String::new()
+                }
             );
         }
     }
@@ -383,14 +506,20 @@ fn print_markdown(s: &DiffSummary) {
         "- Files: {} (A:{} · M:{} · D:{} · R:{})",
         s.files, s.files_added, s.files_modified, s.files_deleted, s.files_renamed
     );
+    let t = &s.totals;
+    let [code, comment, doc, blank] = category_cells(
+        t.churn.as_ref(),
+        [t.code_added, t.comment_added, t.doc_added, t.blank_added],
+        |n| n.to_string(),
+    );
     println!(
-        "- Code Δ: {} · Comment Δ: {} · Blank Δ: {} · Net Δ: {}\n",
-        s.totals.code_added, s.totals.comment_added, s.totals.blank_added, s.totals.total_net
+        "- Code Δ: {} · Comment Δ: {} · Doc Δ: {} · Blank Δ: {} · Net Δ: {}\n",
+        code, comment, doc, blank, t.total_net
     );
 
     println!("#### Top Languages by Net Δ");
-    println!("| Language | files | code Δ | comment Δ | blank Δ | net Δ |");
-    println!("|---------:|-----:|-------:|----------:|--------:|-----:|");
+    println!("| Language | files | code Δ | comment Δ | doc Δ | blank Δ | net Δ |");
+    println!("|---------:|-----:|-------:|----------:|------:|--------:|-----:|");
     let mut langs: Vec<_> = s.languages.iter().collect();
     langs.sort_by(|a, b| {
         b.1.total_net
@@ -398,37 +527,37 @@ fn print_markdown(s: &DiffSummary) {
             .cmp(&a.1.total_net.abs())
             .then(b.0.cmp(a.0))
     });
-    for (lang, d) in langs.into_iter().take(10) {
+    let row = |name: &str, d: &LineDelta| {
+        let [code, comment, doc, blank] = category_cells(
+            d.churn.as_ref(),
+            [d.code_added, d.comment_added, d.doc_added, d.blank_added],
+            |n| n.to_string(),
+        );
         println!(
-            "| {} | {} | {} | {} | {} | {} |",
-            lang, d.files, d.code_added, d.comment_added, d.blank_added, d.total_net
+            "| {} | {} | {} | {} | {} | {} | {} |",
+            name, d.files, code, comment, doc, blank, d.total_net
         );
+    };
+    for (lang, d) in langs.into_iter().take(10) {
+        row(lang, d);
     }
-    println!(
-        "| Total | {} | {} | {} | {} | {} |",
-        s.totals.files,
-        s.totals.code_added,
-        s.totals.comment_added,
-        s.totals.blank_added,
-        s.totals.total_net
-    );
+    row("Total", &s.totals);
 
     if !s.by_file.is_empty() {
         println!("\n<details><summary>Top Changed Files</summary>\n");
-        println!("| File | status | language | code Δ | comment Δ | blank Δ | net Δ |");
-        println!("|------|:------:|:--------:|------:|----------:|--------:|-----:|");
+        println!("| File | status | language | code Δ | comment Δ | doc Δ | blank Δ | net Δ |");
+        println!("|------|:------:|:--------:|------:|----------:|------:|--------:|-----:|");
         let mut files = s.by_file.clone();
-        files.sort_by(|a, b| b.total_delta.abs().cmp(&a.total_delta.abs()));
+        files.sort_by_key(|f| std::cmp::Reverse(f.total_delta.abs()));
         for f in files.into_iter().take(10) {
+            let [code, comment, doc, blank] = category_cells(
+                f.churn.as_ref(),
+                [f.code_delta, f.comment_delta, f.doc_delta, f.blank_delta],
+                |n| n.to_string(),
+            );
             println!(
-                "| {} | {} | {} | {} | {} | {} | {} |",
-                f.path,
-                f.status,
-                f.language,
-                f.code_delta,
-                f.comment_delta,
-                f.blank_delta,
-                f.total_delta
+                "| {} | {} | {} | {} | {} | {} | {} | {} |",
+                f.path, f.status, f.language, code, comment, doc, blank, f.total_delta
             );
         }
         println!("\n</details>");
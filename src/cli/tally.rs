@@ -0,0 +1,342 @@
+use std::path::{Component, Path};
+
+use indexmap::IndexMap;
+
+use crate::codeowners::{CodeOwners, UNOWNED};
+use crate::languages::DetectionSource;
+use crate::types::{AnalyzeResult, FileAnalysis, FileCounts, FileStats, GroupCounts, PerFile};
+
+use super::{Args, SortKey};
+
+// Parent language -> embedded language -> counts
+type EmbeddedMap = IndexMap<String, IndexMap<String, FileCounts>>;
+
+// Ultra mode keeps a single running total under this key instead of per-language counts
+const ULTRA_TOTAL: &str = "__TOTAL__";
+
+/// What gets recorded for each analyzed file: exclusions and optional breakdowns.
+pub(super) struct TallyOptions<'a> {
+    pub include_generated: bool,
+    pub ultra: bool,
+    pub by_file: bool,
+    /// `--by-dir` depth
+    pub by_dir: Option<usize>,
+    pub codeowners: Option<&'a CodeOwners>,
+    /// `--by-file` order and row limit
+    pub sort: SortKey,
+    pub top: Option<usize>,
+    pub skip_empty: bool,
+}
+
+impl<'a> TallyOptions<'a> {
+    pub fn new(args: &Args, codeowners: Option<&'a CodeOwners>) -> Self {
+        Self {
+            include_generated: args.include_generated,
+            ultra: args.ultra,
+            by_file: args.by_file,
+            by_dir: args.by_dir.then_some(args.depth),
+            codeowners,
+            sort: args.sort,
+            top: args.top,
+            skip_empty: args.skip_empty,
+        }
+    }
+}
+
+/// An analyzed file as seen by the tally.
+pub(super) struct FileRecord<'p> {
+    /// Path relative to the scanned root, used for `--by-file`, `--by-dir` and `--by-owner`
+    pub path: &'p Path,
+    pub language: String,
+    /// What decided `language`, shown with `--by-file`
+    pub detected_by: Option<DetectionSource>,
+    pub bytes: u64,
+    /// `linguist-generated` is set for the file
+    pub marked_generated: bool,
+}
+
+/// Counts collected by one worker. Tallies from all workers are merged, then turned into
+/// an `AnalyzeResult` by `finish`.
+#[derive(Debug, Default)]
+pub(super) struct Tally {
+    per_lang: IndexMap<String, FileCounts>,
+    embedded: EmbeddedMap,
+    generated: IndexMap<String, FileCounts>,
+    files: Vec<PerFile>,
+    dirs: IndexMap<String, GroupCounts>,
+    owners: IndexMap<String, GroupCounts>,
+    pub ignored_files: usize,
+    pub empty_files: usize,
+    pub generated_files: usize,
+    pub vendored_files: usize,
+    pub binary_files: usize,
+    pub cache_hits: usize,
+    pub cache_misses: usize,
+}
+
+impl Tally {
+    /// Record one file's analysis. Binary files are only counted; generated files go to
+    /// their own section unless `include_generated`.
+    pub fn record(&mut self, opts: &TallyOptions, file: FileRecord, analysis: FileAnalysis) {
+        if analysis.binary {
+            self.binary_files += 1;
+            return;
+        }
+        if analysis.counts.files == 0 {
+            return;
+        }
+        let generated = file.marked_generated || analysis.generated.is_some();
+        if generated {
+            self.generated_files += 1;
+        }
+        let excluded = generated && !opts.include_generated;
+        if opts.ultra {
+            // In ultra mode, avoid per-language aggregation; accumulate totals only
+            if !excluded {
+                self.per_lang
+                    .entry(ULTRA_TOTAL.to_string())
+                    .or_default()
+                    .merge(&analysis.combined());
+            }
+            return;
+        }
+        if excluded {
+            // Generated files are kept apart, with embedded lines folded into the file
+            self.generated
+                .entry(file.language)
+                .or_default()
+                .merge(&analysis.combined());
+            return;
+        }
+
+        let lang = file.language;
+        if let Some(depth) = opts.by_dir {
+            self.dirs
+                .entry(dir_key(file.path, depth))
+                .or_default()
+                .add(&lang, &analysis);
+        }
+        if let Some(codeowners) = opts.codeowners {
+            let owners = codeowners.owners(file.path);
+            if owners.is_empty() {
+                self.owners
+                    .entry(UNOWNED.to_string())
+                    .or_default()
+                    .add(&lang, &analysis);
+            }
+            for owner in owners {
+                self.owners
+                    .entry(owner.clone())
+                    .or_default()
+                    .add(&lang, &analysis);
+            }
+        }
+        if opts.by_file {
+            let all = analysis.combined();
+            self.files.push(PerFile {
+                path: file.path.display().to_string(),
+                language: lang.clone(),
+                code: all.code,
+                comment: all.comment,
+                blank: all.blank,
+                doc: all.doc,
+                total: all.total,
+                bytes: file.bytes,
+                detected_by: file.detected_by,
+            });
+        }
+
+        // Embedded lines count towards their own language (without adding a file)
+        // and are also recorded under the parent for the breakdown
+        for (child, counts) in &analysis.embedded {
+            self.per_lang
+                .entry(child.clone())
+                .or_default()
+                .merge(counts);
+            self.embedded
+                .entry(lang.clone())
+                .or_default()
+                .entry(child.clone())
+                .or_default()
+                .merge(counts);
+        }
+        self.per_lang
+            .entry(lang)
+            .or_default()
+            .merge(&analysis.counts);
+    }
+
+    pub fn merge(&mut self, other: Tally) {
+        for (lang, counts) in other.per_lang {
+            self.per_lang.entry(lang).or_default().merge(&counts);
+        }
+        for (parent, children) in other.embedded {
+            let p = self.embedded.entry(parent).or_default();
+            for (child, counts) in children {
+                p.entry(child).or_default().merge(&counts);
+            }
+        }
+        for (lang, counts) in other.generated {
+            self.generated.entry(lang).or_default().merge(&counts);
+        }
+        self.files.extend(other.files);
+        for (dir, counts) in other.dirs {
+            self.dirs.entry(dir).or_default().merge(&counts);
+        }
+        for (owner, counts) in other.owners {
+            self.owners.entry(owner).or_default().merge(&counts);
+        }
+        self.ignored_files += other.ignored_files;
+        self.empty_files += other.empty_files;
+        self.generated_files += other.generated_files;
+        self.vendored_files += other.vendored_files;
+        self.binary_files += other.binary_files;
+        self.cache_hits += other.cache_hits;
+        self.cache_misses += other.cache_misses;
+    }
+
+    /// Sort every section and assemble the result. `total_files` is the number of files
+    /// visited, analyzed or not.
+    pub fn finish(
+        self,
+        opts: &TallyOptions,
+        total_files: usize,
+        elapsed_seconds: f64,
+        analyzed_path: String,
+    ) -> AnalyzeResult {
+        let by_code = |ka: &String, a: &FileCounts, kb: &String, b: &FileCounts| {
+            b.code.cmp(&a.code).then_with(|| ka.cmp(kb))
+        };
+
+        let mut by_file = self.files;
+        sort_files(&mut by_file, opts.sort);
+        if let Some(n) = opts.top {
+            by_file.truncate(n);
+        }
+
+        let mut by_dir = self.dirs;
+        by_dir.sort_keys();
+        for dir in by_dir.values_mut() {
+            dir.languages.sort_by(by_code);
+        }
+
+        let mut by_owner = self.owners;
+        by_owner.sort_by(|ka, a, kb, b| by_code(ka, &a.totals, kb, &b.totals));
+        for owner in by_owner.values_mut() {
+            owner.languages.sort_by(by_code);
+        }
+
+        let mut embedded = self.embedded;
+        embedded.sort_keys();
+        for children in embedded.values_mut() {
+            children.sort_by(by_code);
+        }
+        let mut generated = self.generated;
+        generated.sort_by(by_code);
+
+        let mut per_lang: IndexMap<String, FileCounts> = IndexMap::new();
+        let mut totals = FileCounts::default();
+        if opts.ultra {
+            if let Some(total_counts) = self.per_lang.get(ULTRA_TOTAL) {
+                totals = *total_counts;
+            }
+        } else {
+            for (lang, counts) in self.per_lang.iter() {
+                per_lang.insert(lang.clone(), *counts);
+                totals.merge(counts);
+            }
+        }
+
+        // Sort per_lang by descending code (then total) before serializing/printing
+        per_lang.sort_by(|ka, a, kb, b| {
+            b.code
+                .cmp(&a.code)
+                .then_with(|| b.total.cmp(&a.total))
+                .then_with(|| ka.cmp(kb))
+        });
+
+        let stats = FileStats {
+            total_files,
+            unique_files: if opts.ultra {
+                totals.files
+            } else {
+                self.per_lang.values().map(|c| c.files).sum::<usize>()
+            },
+            ignored_files: self.ignored_files,
+            empty_files: if opts.skip_empty { 0 } else { self.empty_files },
+            generated_files: self.generated_files,
+            vendored_files: self.vendored_files,
+            binary_files: self.binary_files,
+            cache_hits: self.cache_hits,
+            cache_misses: self.cache_misses,
+            elapsed_seconds,
+        };
+
+        AnalyzeResult {
+            per_lang,
+            totals,
+            embedded,
+            generated,
+            by_file,
+            by_dir,
+            by_owner,
+            files_analyzed: totals.files,
+            stats: Some(stats),
+            analyzed_path: Some(analyzed_path),
+        }
+    }
+}
+
+/// Order per-file rows by `key`: counts and sizes descending, path and language ascending,
+/// ties broken by path.
+fn sort_files(files: &mut [PerFile], key: SortKey) {
+    files.sort_by(|a, b| {
+        let primary = match key {
+            SortKey::Path => std::cmp::Ordering::Equal,
+            SortKey::Language => a.language.cmp(&b.language),
+            SortKey::Code => b.code.cmp(&a.code),
+            SortKey::Comment => b.comment.cmp(&a.comment),
+            SortKey::Blank => b.blank.cmp(&a.blank),
+            SortKey::Doc => b.doc.cmp(&a.doc),
+            SortKey::Total => b.total.cmp(&a.total),
+            SortKey::Bytes => b.bytes.cmp(&a.bytes),
+        };
+        primary.then_with(|| a.path.cmp(&b.path))
+    });
+}
+
+/// `--by-dir` group of a file: the first `depth` directories of its path relative to the
+/// scanned root, joined with `/`; files above that depth group under their own directory
+/// (`.` for the root itself).
+fn dir_key(rel: &Path, depth: usize) -> String {
+    let dirs: Vec<String> = rel
+        .parent()
+        .into_iter()
+        .flat_map(|p| p.components())
+        .filter_map(|c| match c {
+            Component::Normal(name) => Some(name.to_string_lossy().into_owned()),
+            _ => None,
+        })
+        .take(depth)
+        .collect();
+    if dirs.is_empty() {
+        ".".to_string()
+    } else {
+        dirs.join("/")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dir_keys_keep_the_requested_depth() {
+        let key = |p: &str, depth| dir_key(Path::new(p), depth);
+        assert_eq!(key("services/auth/src/main.rs", 2), "services/auth");
+        assert_eq!(key("services/auth/src/main.rs", 1), "services");
+        assert_eq!(key("libs/core.rs", 2), "libs");
+        assert_eq!(key("build.rs", 2), ".");
+        assert_eq!(key("main.rs", 0), ".");
+    }
+}
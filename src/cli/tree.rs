@@ -0,0 +1,152 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use anyhow::Result;
+use git2::{Oid, Repository};
+use rayon::prelude::*;
+
+use crate::analyzer::Analyzer;
+use crate::cache::{BlobAnalysis, BlobCache};
+use crate::vcs::{TreeAttributes, VcsContext};
+
+use super::tally::{FileRecord, Tally, TallyOptions};
+
+// Blob analyses by (blob id, path, `linguist-language`), kept across the trees counted by
+// one `TreeCount`: a file unchanged between commits is looked up once. `None` marks blobs
+// that could not be read.
+type SeenBlobs = HashMap<(Oid, PathBuf, Option<String>), Option<BlobAnalysis>>;
+
+/// Counts the files of a revision's tree, reading blobs from the object database and
+/// `.gitattributes` from the tree itself. Shared by `--rev` and `history`.
+pub(super) struct TreeCount<'a> {
+    pub analyzer: &'a Analyzer,
+    pub cache: &'a BlobCache,
+    pub opts: &'a TallyOptions<'a>,
+    pub allowed_exts: Option<&'a HashSet<String>>,
+    pub min_size: Option<u64>,
+    pub max_size: Option<u64>,
+    /// Remember analyses between calls to `count`, for trees sharing most of their blobs
+    pub seen: Option<Mutex<SeenBlobs>>,
+}
+
+impl TreeCount<'_> {
+    /// Tally `files`, the blobs under the repository-relative directory `prefix` in the tree
+    /// of `rev` (paths relative to `prefix`, as from `VcsContext::tree_files`).
+    pub fn count(
+        &self,
+        vcs: &VcsContext,
+        rev: &str,
+        prefix: &Path,
+        files: &[(PathBuf, Oid)],
+    ) -> Result<Tally> {
+        let attributes = vcs.tree_attributes(rev)?;
+        // git2 repositories are not shared between threads: each fold opens its own handle
+        let repo_path = vcs.repo.path();
+        let tally = files
+            .par_iter()
+            .fold(
+                || (None::<Repository>, Tally::default()),
+                |(mut repo, mut tally), (path, oid)| {
+                    if repo.is_none() {
+                        repo = Repository::open(repo_path).ok();
+                    }
+                    if let Some(repo) = &repo {
+                        self.count_file(repo, &attributes, &mut tally, prefix, path, *oid);
+                    }
+                    (repo, tally)
+                },
+            )
+            .map(|(_, tally)| tally)
+            .reduce(Tally::default, |mut a, b| {
+                a.merge(b);
+                a
+            });
+        Ok(tally)
+    }
+
+    fn count_file(
+        &self,
+        repo: &Repository,
+        attributes: &TreeAttributes,
+        tally: &mut Tally,
+        prefix: &Path,
+        path: &Path,
+        oid: Oid,
+    ) {
+        if let Some(allowed) = self.allowed_exts {
+            let ext = path.extension().and_then(|e| e.to_str());
+            if !ext.is_some_and(|e| allowed.contains(&e.to_ascii_lowercase())) {
+                return;
+            }
+        }
+        let attrs = attributes.lookup(&prefix.join(path));
+
+        // Vendored blobs are skipped unread; generated ones are analyzed for their own
+        // section
+        if attrs.vendored && !attrs.generated {
+            tally.vendored_files += 1;
+            if !self.opts.include_generated {
+                return;
+            }
+        }
+
+        // Size filters use the object header, before the blob is read
+        if self.min_size.is_some() || self.max_size.is_some() || self.opts.skip_empty {
+            let Some(size) = repo
+                .odb()
+                .ok()
+                .and_then(|odb| odb.read_header(oid).ok())
+                .map(|(size, _)| size as u64)
+            else {
+                return;
+            };
+            if self.min_size.is_some_and(|min| size < min)
+                || self.max_size.is_some_and(|max| size > max)
+            {
+                return;
+            }
+            if self.opts.skip_empty && size == 0 {
+                tally.empty_files += 1;
+                return;
+            }
+        }
+
+        let analyze = || {
+            self.cache
+                .analyze_blob_as(self.analyzer, repo, path, oid, attrs.language.as_deref())
+        };
+        let blob = match &self.seen {
+            Some(seen) => {
+                let key = (oid, path.to_path_buf(), attrs.language.clone());
+                let known = seen.lock().ok().and_then(|s| s.get(&key).cloned());
+                match known {
+                    Some(blob) => blob,
+                    None => {
+                        let blob = analyze();
+                        if let Ok(mut seen) = seen.lock() {
+                            seen.insert(key, blob.clone());
+                        }
+                        blob
+                    }
+                }
+            }
+            None => analyze(),
+        };
+        let Some(blob) = blob else {
+            return;
+        };
+        let Some(language) = blob.language else {
+            tally.ignored_files += 1;
+            return;
+        };
+        let file = FileRecord {
+            path,
+            language,
+            detected_by: blob.detected_by,
+            bytes: blob.bytes,
+            marked_generated: attrs.generated,
+        };
+        tally.record(self.opts, file, blob.analysis);
+    }
+}
@@ -0,0 +1,141 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+
+use crate::vcs::work_tree_of;
+
+/// Where a `CODEOWNERS` file is looked up, in GitHub's order of precedence.
+pub const CODEOWNERS_LOCATIONS: &[&str] = &[".github/CODEOWNERS", "CODEOWNERS", "docs/CODEOWNERS"];
+
+/// Owner reported for files no rule matches, or whose matching rule lists no owners.
+pub const UNOWNED: &str = "(unowned)";
+
+struct Rule {
+    matcher: Gitignore,
+    owners: Vec<String>,
+}
+
+/// Parsed `CODEOWNERS` rules. Patterns use gitignore syntax relative to the repository
+/// root, and the last matching rule decides a file's owners.
+pub struct CodeOwners {
+    rules: Vec<Rule>,
+    // Directory the looked-up paths are relative to, from the repository root
+    prefix: PathBuf,
+}
+
+impl CodeOwners {
+    /// Load the first `CODEOWNERS` file of the work tree containing `root` (of `root`
+    /// itself outside git), if any. Paths passed to `owners` are relative to `root`.
+    pub fn discover(root: &Path) -> Result<Option<Self>> {
+        let (base, prefix) = match work_tree_of(root) {
+            Some((repo, prefix)) => match repo.workdir() {
+                Some(workdir) => (workdir.to_path_buf(), prefix),
+                None => (root.to_path_buf(), PathBuf::new()),
+            },
+            None => (root.to_path_buf(), PathBuf::new()),
+        };
+        for location in CODEOWNERS_LOCATIONS {
+            let path = base.join(location);
+            if path.is_file() {
+                let text = fs::read_to_string(&path)
+                    .with_context(|| format!("read {}", path.display()))?;
+                return Ok(Some(Self::parse(&base, &text).within(&prefix)));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Look up paths relative to `prefix`, a directory below the repository root (the
+    /// scanned subdirectory), instead of relative to the root itself.
+    pub fn within(mut self, prefix: &Path) -> Self {
+        self.prefix = prefix.to_path_buf();
+        self
+    }
+
+    /// Parse `CODEOWNERS` content. Lines with an invalid pattern are skipped, as GitHub does.
+    pub fn parse(root: &Path, text: &str) -> Self {
+        let mut rules = Vec::new();
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut fields = line.split_whitespace();
+            let Some(pattern) = fields.next() else {
+                continue;
+            };
+            let owners = fields
+                .take_while(|f| !f.starts_with('#'))
+                .map(str::to_string)
+                .collect();
+            // One matcher per rule: a later rule wins even when an earlier one matches the
+            // file itself and the later one only its directory
+            let mut builder = GitignoreBuilder::new(root);
+            if builder.add_line(None, pattern).is_err() {
+                continue;
+            }
+            if let Ok(matcher) = builder.build() {
+                rules.push(Rule { matcher, owners });
+            }
+        }
+        Self {
+            rules,
+            prefix: PathBuf::new(),
+        }
+    }
+
+    /// Owners of the file at `rel` (relative to the root, or to the `within` directory);
+    /// empty when unowned.
+    pub fn owners(&self, rel: &Path) -> &[String] {
+        let rel = self.prefix.join(rel);
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| {
+                rule.matcher
+                    .matched_path_or_any_parents(&rel, false)
+                    .is_ignore()
+            })
+            .map(|rule| rule.owners.as_slice())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn last_matching_rule_wins() {
+        let owners = CodeOwners::parse(
+            Path::new("/repo"),
+            "# default owners\n\
+             *                @org/everyone\n\
+             *.js             @org/web @alice # inline comment\n\
+             /docs/           @org/docs\n\
+             services/auth/** @org/auth\n\
+             /vendor/\n",
+        );
+        let of = |p: &str| owners.owners(Path::new(p)).to_vec();
+        assert_eq!(of("main.rs"), ["@org/everyone"]);
+        assert_eq!(of("web/app.js"), ["@org/web", "@alice"]);
+        // `/docs/` comes after `*.js`, so it owns JavaScript under docs/ too
+        assert_eq!(of("docs/guide/x.js"), ["@org/docs"]);
+        assert_eq!(of("services/auth/src/lib.rs"), ["@org/auth"]);
+        assert_eq!(of("services/billing/lib.rs"), ["@org/everyone"]);
+        // A rule without owners leaves its files unowned
+        assert!(of("vendor/lib.c").is_empty());
+        assert!(
+            CodeOwners::parse(Path::new("/repo"), "/src/ @a\n")
+                .owners(Path::new("main.rs"))
+                .is_empty()
+        );
+        // Paths under a scanned subdirectory are matched from the root
+        let within = CodeOwners::parse(Path::new("/repo"), "/services/auth/ @org/auth\n")
+            .within(Path::new("services"));
+        assert_eq!(within.owners(Path::new("auth/lib.rs")), ["@org/auth"]);
+        assert!(within.owners(Path::new("billing/lib.rs")).is_empty());
+    }
+}
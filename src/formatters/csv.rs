@@ -1,28 +1,81 @@
-use crate::types::{AnalyzeResult, FileCounts};
+use std::borrow::Cow;
+
+use indexmap::IndexMap;
+
+use crate::types::{AnalyzeResult, FileCounts, GroupCounts};
 
 pub fn format(a: &AnalyzeResult) -> String {
     let mut out = String::new();
-    out.push_str("language,files,code,comment,blank,total\n");
+    // `doc` is appended so existing column positions stay stable
+    out.push_str("language,files,code,comment,blank,total,doc\n");
     for (lang, c) in &a.per_lang {
-        push_row(&mut out, lang, c);
+        push_row(&mut out, &field(lang), c);
     }
     push_row(&mut out, "Total", &a.totals);
+
+    push_groups(&mut out, "directory", &a.by_dir);
+    push_groups(&mut out, "owner", &a.by_owner);
+
+    if !a.by_file.is_empty() {
+        use std::fmt::Write as _;
+        out.push_str("\npath,language,code,comment,blank,total,doc,bytes,detected_by\n");
+        for f in &a.by_file {
+            let _ = writeln!(
+                out,
+                "{},{},{},{},{},{},{},{},{}",
+                field(&f.path),
+                field(&f.language),
+                f.code,
+                f.comment,
+                f.blank,
+                f.total,
+                f.doc,
+                f.bytes,
+                f.detected_by.map_or("", |d| d.as_str())
+            );
+        }
+    }
     out
 }
 
+/// One row per group and language, then the group's total.
+fn push_groups(out: &mut String, kind: &str, groups: &IndexMap<String, GroupCounts>) {
+    if groups.is_empty() {
+        return;
+    }
+    out.push_str(&format!(
+        "\n{kind},language,files,code,comment,blank,total,doc\n"
+    ));
+    for (name, group) in groups {
+        for (lang, c) in &group.languages {
+            push_row(out, &format!("{},{}", field(name), field(lang)), c);
+        }
+        push_row(out, &format!("{},Total", field(name)), &group.totals);
+    }
+}
+
+/// A cell quoted as RFC 4180 requires: in double quotes, with quotes doubled, when it
+/// contains a comma, a quote or a line break.
+fn field(s: &str) -> Cow<'_, str> {
+    if s.contains([',', '"', '\r', '\n']) {
+        Cow::Owned(format!("\"{}\"", s.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(s)
+    }
+}
+
 fn push_row(out: &mut String, lang: &str, c: &FileCounts) {
     use std::fmt::Write as _;
     let _ = writeln!(
         out,
-        "{},{},{},{},{},{}",
-        lang, c.files, c.code, c.comment, c.blank, c.total
+        "{},{},{},{},{},{},{}",
+        lang, c.files, c.code, c.comment, c.blank, c.total, c.doc
     );
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use indexmap::IndexMap;
 
     #[test]
     fn csv_includes_new_languages() {
@@ -35,6 +88,7 @@ mod tests {
                 code: 8,
                 comment: 1,
                 blank: 1,
+                doc: 0,
             },
         );
         per.insert(
@@ -45,6 +99,7 @@ mod tests {
                 code: 3,
                 comment: 2,
                 blank: 0,
+                doc: 0,
             },
         );
         let mut totals = FileCounts::default();
@@ -54,6 +109,11 @@ mod tests {
         let a = AnalyzeResult {
             per_lang: per,
             totals,
+            embedded: IndexMap::new(),
+            generated: IndexMap::new(),
+            by_file: Vec::new(),
+            by_dir: IndexMap::new(),
+            by_owner: IndexMap::new(),
             files_analyzed: totals.files,
             stats: None,
             analyzed_path: None,
@@ -64,4 +124,52 @@ mod tests {
         assert!(out.contains("SVG"));
         assert!(out.contains("\nTotal,"));
     }
+
+    #[test]
+    fn csv_quotes_paths_and_group_names() {
+        let c = FileCounts {
+            files: 1,
+            total: 3,
+            code: 2,
+            comment: 1,
+            blank: 0,
+            doc: 0,
+        };
+        let group = GroupCounts {
+            totals: c,
+            languages: IndexMap::from([("Rust".to_string(), c)]),
+        };
+        let a = AnalyzeResult {
+            per_lang: IndexMap::from([("Rust".to_string(), c)]),
+            totals: c,
+            embedded: IndexMap::new(),
+            generated: IndexMap::new(),
+            by_file: vec![crate::types::PerFile {
+                path: "src/a,b.rs".to_string(),
+                language: "Rust".to_string(),
+                code: 2,
+                comment: 1,
+                total: 3,
+                bytes: 20,
+                ..Default::default()
+            }],
+            by_dir: IndexMap::from([("say \"hi\"".to_string(), group.clone())]),
+            by_owner: IndexMap::from([("@org/a, @org/b".to_string(), group)]),
+            files_analyzed: 1,
+            stats: None,
+            analyzed_path: None,
+        };
+        let out = format(&a);
+        assert!(
+            out.contains("\n\"src/a,b.rs\",Rust,2,1,0,3,0,20,\n"),
+            "{out}"
+        );
+        assert!(
+            out.contains("\n\"say \"\"hi\"\"\",Rust,1,2,1,0,3,0\n"),
+            "{out}"
+        );
+        assert!(out.contains("\n\"say \"\"hi\"\"\",Total,"), "{out}");
+        assert!(out.contains("\n\"@org/a, @org/b\",Rust,"), "{out}");
+        assert!(out.contains("\nRust,1,2,1,0,3,0\n"), "{out}");
+    }
 }
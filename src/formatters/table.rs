@@ -1,4 +1,4 @@
-use crate::types::{AnalyzeResult, FileCounts};
+use crate::types::{AnalyzeResult, FileCounts, PerFile};
 use chrono::Local;
 
 pub fn format(a: &AnalyzeResult) -> String {
@@ -51,6 +51,24 @@ pub fn format(a: &AnalyzeResult) -> String {
                 format_num(stats.empty_files)
             ));
         }
+        if stats.generated_files > 0 {
+            output.push_str(&format!(
+                "  Generated     : {:>10}\n",
+                format_num(stats.generated_files)
+            ));
+        }
+        if stats.vendored_files > 0 {
+            output.push_str(&format!(
+                "  Vendored      : {:>10}\n",
+                format_num(stats.vendored_files)
+            ));
+        }
+        if stats.binary_files > 0 {
+            output.push_str(&format!(
+                "  Binary Files  : {:>10}\n",
+                format_num(stats.binary_files)
+            ));
+        }
         output.push_str("─────────────────────────────────────\n\n");
 
         // Show performance statistics
@@ -72,6 +90,16 @@ pub fn format(a: &AnalyzeResult) -> String {
         let total_lines = a.totals.total;
         let lines_per_sec = total_lines as f64 / denom;
         output.push_str(&format!("  Lines/sec     : {:>10.0}\n", lines_per_sec));
+        if stats.cache_hits + stats.cache_misses > 0 {
+            output.push_str(&format!(
+                "  Cache Hits    : {:>10}\n",
+                format_num(stats.cache_hits)
+            ));
+            output.push_str(&format!(
+                "  Cache Misses  : {:>10}\n",
+                format_num(stats.cache_misses)
+            ));
+        }
         output.push_str("─────────────────────────────────────\n\n");
     }
     // Compute dynamic column widths with more generous minimums
@@ -79,6 +107,7 @@ pub fn format(a: &AnalyzeResult) -> String {
     let mut files_w: usize = 8; // increased for "files" header
     let mut code_w: usize = 10; // increased for larger numbers
     let mut comm_w: usize = 10; // increased for "comment" header
+    let mut doc_w: usize = 8;
     let mut blank_w: usize = 10; // increased for consistency
     let mut total_w: usize = 10; // increased for consistency
 
@@ -93,21 +122,34 @@ pub fn format(a: &AnalyzeResult) -> String {
         .per_lang
         .keys()
         .map(|s| s.len())
+        .chain(embedded_rows(a).map(|(name, _)| name.len()))
+        .chain(a.generated.keys().map(|s| s.len() + 2))
+        .chain(
+            [&a.by_dir, &a.by_owner]
+                .into_iter()
+                .flat_map(|groups| groups.iter())
+                .flat_map(|(name, group)| {
+                    let langs = group.languages.keys().map(|s| s.len() + 2);
+                    std::iter::once(name.len()).chain(langs)
+                }),
+        )
         .chain(std::iter::once("Total".len()))
         .max()
         .unwrap_or(lang_w)
         .max(12); // increased from 8 to match new minimum
 
-    for c in a.per_lang.values() {
+    for c in a.per_lang.values().chain(a.generated.values()) {
         update_w(&mut files_w, c.files);
         update_w(&mut code_w, c.code);
         update_w(&mut comm_w, c.comment);
+        update_w(&mut doc_w, c.doc);
         update_w(&mut blank_w, c.blank);
         update_w(&mut total_w, c.total);
     }
     update_w(&mut files_w, a.totals.files);
     update_w(&mut code_w, a.totals.code);
     update_w(&mut comm_w, a.totals.comment);
+    update_w(&mut doc_w, a.totals.doc);
     update_w(&mut blank_w, a.totals.blank);
     update_w(&mut total_w, a.totals.total);
 
@@ -121,6 +163,7 @@ pub fn format(a: &AnalyzeResult) -> String {
         files: files_w,
         blank: blank_w,
         comm: comm_w,
+        doc: doc_w,
         code: code_w,
         total: total_w,
     };
@@ -130,18 +173,20 @@ pub fn format(a: &AnalyzeResult) -> String {
     let h_files = format!("{:>w$}", "files", w = widths.files);
     let h_blank = format!("{:>w$}", "blank", w = widths.blank);
     let h_comm = format!("{:>w$}", "comment", w = widths.comm);
+    let h_doc = format!("{:>w$}", "doc", w = widths.doc);
     let h_code = format!("{:>w$}", "code", w = widths.code);
     let h_total = format!("{:>w$}", "Total", w = widths.total);
-    let header = [h_lang, h_files, h_blank, h_comm, h_code, h_total].join(&sep);
+    let header = [h_lang, h_files, h_blank, h_comm, h_doc, h_code, h_total].join(&sep);
 
     // Create a separator line that matches the total width of the table
     let sep_len = widths.lang
         + widths.files
         + widths.blank
         + widths.comm
+        + widths.doc
         + widths.code
         + widths.total
-        + gutter * 5;
+        + gutter * 6;
     let separator = "-".repeat(sep_len);
 
     let mut lines = Vec::new();
@@ -159,56 +204,115 @@ pub fn format(a: &AnalyzeResult) -> String {
     let total_line = format_row("Total", &a.totals, &widths, &sep);
     lines.push(total_line);
     // Add bottom separator to close the table
-    lines.push(separator);
+    lines.push(separator.clone());
+
+    // Breakdown of languages embedded in other files (already included above)
+    if !a.embedded.is_empty() {
+        lines.push(String::new());
+        lines.push("Embedded:".to_string());
+        lines.push(separator.clone());
+        for (name, counts) in embedded_rows(a) {
+            lines.push(format_row(&name, counts, &widths, &sep));
+        }
+        lines.push(separator.clone());
+    }
+
+    // Generated files are reported on their own and left out of the totals
+    if !a.generated.is_empty() {
+        lines.push(String::new());
+        lines.push("Generated (not in totals):".to_string());
+        lines.push(separator.clone());
+        for (lang, counts) in &a.generated {
+            lines.push(format_row(&format!("  {lang}"), counts, &widths, &sep));
+        }
+        lines.push(separator.clone());
+    }
+
+    // Groups: each group's totals, then its languages indented below
+    for (title, groups) in [("Directories:", &a.by_dir), ("Owners:", &a.by_owner)] {
+        if groups.is_empty() {
+            continue;
+        }
+        lines.push(String::new());
+        lines.push(title.to_string());
+        lines.push(separator.clone());
+        for (name, group) in groups {
+            lines.push(format_row(name, &group.totals, &widths, &sep));
+            for (lang, counts) in &group.languages {
+                lines.push(format_row(&format!("  {lang}"), counts, &widths, &sep));
+            }
+        }
+        lines.push(separator.clone());
+    }
+
+    if !a.by_file.is_empty() {
+        lines.push(String::new());
+        lines.extend(file_rows(&a.by_file));
+    }
 
     output.push_str(&lines.join("\n"));
     output
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use indexmap::IndexMap;
-
-    #[test]
-    fn table_includes_new_languages() {
-        let mut per: IndexMap<String, FileCounts> = IndexMap::new();
-        per.insert(
-            "INI".to_string(),
-            FileCounts {
-                files: 3,
-                total: 9,
-                code: 6,
-                comment: 2,
-                blank: 1,
-            },
-        );
-        per.insert(
-            "Text".to_string(),
-            FileCounts {
-                files: 1,
-                total: 4,
-                code: 4,
-                comment: 0,
-                blank: 0,
-            },
-        );
-        let mut totals = FileCounts::default();
-        for v in per.values() {
-            totals.merge(v);
+/// The `--by-file` listing: path and language, then counts and size in bytes.
+fn file_rows(files: &[PerFile]) -> Vec<String> {
+    const HEADERS: [&str; 6] = ["code", "comment", "blank", "doc", "total", "bytes"];
+    let numbers: Vec<[String; 6]> = files
+        .iter()
+        .map(|f| {
+            [
+                format_num(f.code),
+                format_num(f.comment),
+                format_num(f.blank),
+                format_num(f.doc),
+                format_num(f.total),
+                format_num(f.bytes as usize),
+            ]
+        })
+        .collect();
+    let path_w = files.iter().map(|f| f.path.len()).max().unwrap_or(0).max(4);
+    let lang_w = files
+        .iter()
+        .map(|f| f.language.len())
+        .max()
+        .unwrap_or(0)
+        .max(8);
+    let mut num_w = [0usize; 6];
+    for (i, w) in num_w.iter_mut().enumerate() {
+        *w = numbers
+            .iter()
+            .map(|row| row[i].len())
+            .max()
+            .unwrap_or(0)
+            .max(HEADERS[i].len());
+    }
+    let sep = "    ";
+    let row = |path: &str, lang: &str, cells: &[&str]| {
+        let mut line = format!("{:<path_w$}{sep}{:<lang_w$}", path, lang);
+        for (cell, w) in cells.iter().zip(num_w) {
+            line.push_str(&format!("{sep}{:>w$}", cell));
         }
-        let a = AnalyzeResult {
-            per_lang: per,
-            totals,
-            files_analyzed: totals.files,
-            stats: None,
-            analyzed_path: None,
-        };
-        let out = format(&a);
-        assert!(out.contains("INI"));
-        assert!(out.contains("Text"));
-        assert!(out.contains("Total"));
+        line
+    };
+
+    let header = row("File", "Language", &HEADERS);
+    let separator = "-".repeat(header.len());
+    let mut lines = vec![header, separator.clone()];
+    for (f, cells) in files.iter().zip(&numbers) {
+        let cells: Vec<&str> = cells.iter().map(String::as_str).collect();
+        lines.push(row(&f.path, &f.language, &cells));
     }
+    lines.push(separator);
+    lines
+}
+
+/// Rows of the embedded breakdown, labelled like `  JavaScript (in HTML)`.
+fn embedded_rows(a: &AnalyzeResult) -> impl Iterator<Item = (String, &FileCounts)> {
+    a.embedded.iter().flat_map(|(parent, children)| {
+        children
+            .iter()
+            .map(move |(child, counts)| (format!("  {child} (in {parent})"), counts))
+    })
 }
 
 struct ColWidths {
@@ -216,6 +320,7 @@ struct ColWidths {
     files: usize,
     blank: usize,
     comm: usize,
+    doc: usize,
     code: usize,
     total: usize,
 }
@@ -226,6 +331,7 @@ fn format_row(lang: &str, c: &FileCounts, w: &ColWidths, sep: &str) -> String {
     let files_plain = format!("{:>w$}", format_num(c.files), w = w.files);
     let blank_plain = format!("{:>w$}", format_num(c.blank), w = w.blank);
     let comm_plain = format!("{:>w$}", format_num(c.comment), w = w.comm);
+    let doc_plain = format!("{:>w$}", format_num(c.doc), w = w.doc);
     let code_plain = format!("{:>w$}", format_num(c.code), w = w.code);
     let total_plain = format!("{:>w$}", format_num(c.total), w = w.total);
 
@@ -234,6 +340,7 @@ fn format_row(lang: &str, c: &FileCounts, w: &ColWidths, sep: &str) -> String {
         files_plain,
         blank_plain,
         comm_plain,
+        doc_plain,
         code_plain,
         total_plain,
     ]
@@ -259,3 +366,56 @@ fn format_num(n: usize) -> String {
 }
 
 // Colors removed: produce plain, deterministic table output
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indexmap::IndexMap;
+
+    #[test]
+    fn table_includes_new_languages() {
+        let mut per: IndexMap<String, FileCounts> = IndexMap::new();
+        per.insert(
+            "INI".to_string(),
+            FileCounts {
+                files: 3,
+                total: 9,
+                code: 6,
+                comment: 2,
+                blank: 1,
+                doc: 0,
+            },
+        );
+        per.insert(
+            "Text".to_string(),
+            FileCounts {
+                files: 1,
+                total: 4,
+                code: 4,
+                comment: 0,
+                blank: 0,
+                doc: 0,
+            },
+        );
+        let mut totals = FileCounts::default();
+        for v in per.values() {
+            totals.merge(v);
+        }
+        let a = AnalyzeResult {
+            per_lang: per,
+            totals,
+            embedded: IndexMap::new(),
+            generated: IndexMap::new(),
+            by_file: Vec::new(),
+            by_dir: IndexMap::new(),
+            by_owner: IndexMap::new(),
+            files_analyzed: totals.files,
+            stats: None,
+            analyzed_path: None,
+        };
+        let out = format(&a);
+        assert!(out.contains("INI"));
+        assert!(out.contains("Text"));
+        assert!(out.contains("Total"));
+    }
+}
@@ -1,200 +1,886 @@
+use anyhow::{Context, Result, bail};
 use once_cell::sync::Lazy;
-use serde::Deserialize;
-use std::collections::HashMap;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::borrow::Cow;
+use std::cell::OnceCell;
+use std::collections::{BTreeMap, HashMap};
 use std::fs::File;
-use std::io::{BufRead, BufReader};
-use std::path::Path;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LanguageSpec {
     pub name: String,
+    #[serde(default)]
     pub extensions: Vec<String>,
+    /// Line comment markers. Here and in the block and doc comment markers, a marker
+    /// starting with `^` only counts in column 1, e.g. Ruby's `^=begin` or the `^C` of
+    /// fixed-form Fortran
+    #[serde(default)]
     pub line_markers: Vec<String>,
-    pub block_markers: Option<(String, String)>,
+    /// Block comment pairs; accepts `null`, a single `["/*", "*/"]` pair or a list of pairs
+    #[serde(default, deserialize_with = "deserialize_block_markers")]
+    pub block_markers: Vec<(String, String)>,
     #[serde(default)]
     pub special_filenames: Vec<String>,
+    /// String delimiters (start, end) inside which backslash escapes apply
+    #[serde(default)]
+    pub quotes: Vec<(String, String)>,
+    /// String delimiters (start, end) without escapes, e.g. raw strings
+    #[serde(default)]
+    pub verbatim_quotes: Vec<(String, String)>,
+    /// Openers (from `quotes` or `verbatim_quotes`) of strings that may span lines, e.g.
+    /// triple quotes, template literals or raw strings; other strings end with their line
+    #[serde(default)]
+    pub multiline_quotes: Vec<String>,
+    /// Openers of here-documents, e.g. `<<-` and `<<` in shell: the word after one (an
+    /// identifier, optionally quoted) ends the following lines of string content
+    #[serde(default)]
+    pub heredoc_markers: Vec<String>,
+    /// Skip single-quoted character literals such as `'"'` (but not lifetimes)
+    #[serde(default)]
+    pub char_literals: bool,
+    /// Block comments nest, e.g. Rust's `/* outer /* inner */ still comment */`: `true` for
+    /// every pair, or the openers of the pairs that nest, e.g. `["/+"]` in D
+    #[serde(default)]
+    pub nested_blocks: NestedBlocks,
+    /// Openers of docstrings, e.g. `"""` or `@doc """`; each must end with a `quotes` start
+    #[serde(default)]
+    pub doc_string_markers: Vec<String>,
+    /// Documentation line comments, e.g. Rust `///` and `//!`
+    #[serde(default)]
+    pub doc_line_markers: Vec<String>,
+    /// Documentation block comment pairs, e.g. `/** */`; same forms as `block_markers`
+    #[serde(default, deserialize_with = "deserialize_block_markers")]
+    pub doc_block_markers: Vec<(String, String)>,
+    /// Comments directly above a line starting with one of these keywords are
+    /// documentation, e.g. Go package comments above `package`
+    #[serde(default)]
+    pub doc_comment_keywords: Vec<String>,
+    /// Other names for the language, e.g. Markdown fence info strings (`bash`, `golang`)
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    /// How other languages are embedded in this one, if at all
+    #[serde(default)]
+    pub embedded: Option<EmbedSyntax>,
+    /// Shebang interpreter names (after any `env`), e.g. `python3` or `tclsh`; versioned
+    /// names such as `tclsh8.6` match without listing them
+    #[serde(default)]
+    pub interpreters: Vec<String>,
+    /// Tokens that pick this language for an extension shared with others, keyed by
+    /// extension. A token starting with `^` must begin a line (after indentation).
+    #[serde(default)]
+    pub heuristics: BTreeMap<String, Vec<String>>,
+}
+
+/// Kinds of embedded-language regions the analyzer can split a file into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EmbedSyntax {
+    /// `<script>` and `<style>` elements (HTML, Vue, Svelte)
+    Html,
+    /// Fenced code blocks with an info string
+    Markdown,
+}
+
+/// Which block comment pairs of a language nest.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum NestedBlocks {
+    /// Every pair (`true`) or none (`false`)
+    All(bool),
+    /// Only the pairs with these openers
+    Openers(Vec<String>),
+}
+
+impl Default for NestedBlocks {
+    fn default() -> Self {
+        NestedBlocks::All(false)
+    }
+}
+
+impl NestedBlocks {
+    /// Whether the pair opened by `opener` nests.
+    pub fn nests(&self, opener: &[u8]) -> bool {
+        match self {
+            NestedBlocks::All(all) => *all,
+            NestedBlocks::Openers(openers) => openers.iter().any(|o| o.as_bytes() == opener),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum BlockMarkersRepr {
+    Single(String, String),
+    Many(Vec<(String, String)>),
+}
+
+fn deserialize_block_markers<'de, D>(de: D) -> std::result::Result<Vec<(String, String)>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(match Option::<BlockMarkersRepr>::deserialize(de)? {
+        None => Vec::new(),
+        Some(BlockMarkersRepr::Single(a, b)) => vec![(a, b)],
+        Some(BlockMarkersRepr::Many(pairs)) => pairs,
+    })
+}
+
+#[derive(Debug, Clone)]
+pub struct QuotePair {
+    pub start: Vec<u8>,
+    pub end: Vec<u8>,
+    pub escapes: bool,
+    /// The string may continue on the next line
+    pub multiline: bool,
+}
+
+/// Byte-level syntax for one language, precomputed for the analyzer's line scanner.
+#[derive(Debug, Clone)]
+pub struct LanguageSyntax {
+    pub line_markers: Vec<Vec<u8>>,
+    pub block_markers: Vec<(Vec<u8>, Vec<u8>)>,
+    // Per block pair, the opener that nests inside it, if the pair nests
+    pub block_nesting: Vec<Option<Vec<u8>>>,
+    // Ordered longest start first so `"""` wins over `"`
+    pub quotes: Vec<QuotePair>,
+    pub char_literals: bool,
+    // Docstring openers (longest first) and the index of the quote pair they open
+    pub doc_strings: Vec<(Vec<u8>, usize)>,
+    pub doc_line_markers: Vec<Vec<u8>>,
+    pub doc_block_markers: Vec<(Vec<u8>, Vec<u8>)>,
+    // Per doc block pair, the nesting opener: that of the ordinary pair with the same
+    // closer (so `/* */` nests in `/** */`), else the doc pair's own
+    pub doc_block_nesting: Vec<Option<Vec<u8>>>,
+    pub doc_keywords: Vec<Vec<u8>>,
+    // Here-document openers, longest first
+    pub heredocs: Vec<Vec<u8>>,
+    // Bytes that can begin a marker or quote; everything else is plain code
+    lead_bytes: [bool; 256],
+}
+
+// Comment pairs as bytes, longest opener first so `--[==[` wins over `--[[`
+fn pairs_to_bytes(pairs: &[(String, String)]) -> Vec<(Vec<u8>, Vec<u8>)> {
+    let mut out: Vec<(Vec<u8>, Vec<u8>)> = pairs
+        .iter()
+        .filter(|(a, b)| !a.is_empty() && !b.is_empty())
+        .map(|(a, b)| (a.as_bytes().to_vec(), b.as_bytes().to_vec()))
+        .collect();
+    out.sort_by_key(|(a, _)| std::cmp::Reverse(a.len()));
+    out
+}
+
+impl LanguageSyntax {
+    fn from_spec(spec: &LanguageSpec) -> Self {
+        let line_markers: Vec<Vec<u8>> = spec
+            .line_markers
+            .iter()
+            .map(|s| s.as_bytes().to_vec())
+            .collect();
+        let block_markers = pairs_to_bytes(&spec.block_markers);
+        let doc_block_markers = pairs_to_bytes(&spec.doc_block_markers);
+        let nested = &spec.nested_blocks;
+        let block_nesting: Vec<Option<Vec<u8>>> = block_markers
+            .iter()
+            .map(|(a, _)| nested.nests(a).then(|| a.clone()))
+            .collect();
+        let doc_block_nesting = doc_block_markers
+            .iter()
+            .map(
+                |(a, b)| match block_markers.iter().position(|(_, e)| e == b) {
+                    Some(k) => block_nesting[k].clone(),
+                    None => nested.nests(a).then(|| a.clone()),
+                },
+            )
+            .collect();
+        let doc_line_markers: Vec<Vec<u8>> = spec
+            .doc_line_markers
+            .iter()
+            .map(|s| s.as_bytes().to_vec())
+            .collect();
+        let mut quotes: Vec<QuotePair> = spec
+            .quotes
+            .iter()
+            .map(|q| (q, true))
+            .chain(spec.verbatim_quotes.iter().map(|q| (q, false)))
+            .filter(|((a, b), _)| !a.is_empty() && !b.is_empty())
+            .map(|((a, b), escapes)| QuotePair {
+                start: a.as_bytes().to_vec(),
+                end: b.as_bytes().to_vec(),
+                escapes,
+                multiline: spec.multiline_quotes.contains(a),
+            })
+            .collect();
+        quotes.sort_by_key(|q| std::cmp::Reverse(q.start.len()));
+        let mut doc_strings: Vec<(Vec<u8>, usize)> = spec
+            .doc_string_markers
+            .iter()
+            .filter_map(|m| {
+                let marker = m.as_bytes();
+                let quote = quotes.iter().position(|q| marker.ends_with(&q.start))?;
+                Some((marker.to_vec(), quote))
+            })
+            .collect();
+        doc_strings.sort_by_key(|(m, _)| std::cmp::Reverse(m.len()));
+        let mut heredocs: Vec<Vec<u8>> = spec
+            .heredoc_markers
+            .iter()
+            .filter(|m| !m.is_empty())
+            .map(|m| m.as_bytes().to_vec())
+            .collect();
+        heredocs.sort_by_key(|m| std::cmp::Reverse(m.len()));
+
+        let mut lead_bytes = [false; 256];
+        let firsts = line_markers
+            .iter()
+            .map(|m| m.as_slice())
+            .chain(doc_line_markers.iter().map(|m| m.as_slice()))
+            .chain(block_markers.iter().map(|(a, _)| a.as_slice()))
+            .chain(doc_block_markers.iter().map(|(a, _)| a.as_slice()))
+            .chain(quotes.iter().map(|q| q.start.as_slice()))
+            .chain(heredocs.iter().map(|m| m.as_slice()))
+            .filter_map(|m| m.strip_prefix(b"^").unwrap_or(m).first());
+        for &b in firsts {
+            lead_bytes[b as usize] = true;
+        }
+        if spec.char_literals {
+            lead_bytes[b'\'' as usize] = true;
+        }
+        Self {
+            line_markers,
+            block_markers,
+            block_nesting,
+            quotes,
+            char_literals: spec.char_literals,
+            doc_strings,
+            doc_line_markers,
+            doc_block_markers,
+            doc_block_nesting,
+            doc_keywords: spec
+                .doc_comment_keywords
+                .iter()
+                .map(|k| k.as_bytes().to_vec())
+                .collect(),
+            heredocs,
+            lead_bytes,
+        }
+    }
+
+    /// Syntax with no comments or strings, used for unknown languages.
+    pub fn plain() -> Self {
+        Self {
+            line_markers: Vec::new(),
+            block_markers: Vec::new(),
+            block_nesting: Vec::new(),
+            quotes: Vec::new(),
+            char_literals: false,
+            doc_strings: Vec::new(),
+            doc_line_markers: Vec::new(),
+            doc_block_markers: Vec::new(),
+            doc_block_nesting: Vec::new(),
+            doc_keywords: Vec::new(),
+            heredocs: Vec::new(),
+            lead_bytes: [false; 256],
+        }
+    }
+
+    /// Whether `b` may start a comment marker, quote or char literal.
+    #[inline]
+    pub fn is_lead_byte(&self, b: u8) -> bool {
+        self.lead_bytes[b as usize]
+    }
+}
+
+/// How many leading bytes of a file heuristics, modelines and shebang detection look at
+/// (and trailing bytes, for modelines at the end).
+pub const DETECTION_SAMPLE_LEN: usize = 8 * 1024;
+
+// Lines at either end of a file searched for modelines (Vim's default `modelines`)
+const MODELINE_LINES: usize = 5;
+
+// Template extensions (`config.yaml.in`) that a modeline overrides
+const MODELINE_EXTENSIONS: &[&str] = &["in", "tmpl"];
+
+/// What decided a file's language.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DetectionSource {
+    Filename,
+    Extension,
+    Heuristic,
+    Modeline,
+    Shebang,
+    /// A `linguist-language` attribute in `.gitattributes`
+    Attribute,
+}
+
+impl DetectionSource {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DetectionSource::Filename => "filename",
+            DetectionSource::Extension => "extension",
+            DetectionSource::Heuristic => "heuristic",
+            DetectionSource::Modeline => "modeline",
+            DetectionSource::Shebang => "shebang",
+            DetectionSource::Attribute => "attribute",
+        }
+    }
+}
+
+impl std::fmt::Display for DetectionSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.pad(self.as_str())
+    }
+}
+
+/// A detected language and how it was found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Detection {
+    pub index: usize,
+    pub source: DetectionSource,
 }
 
 pub struct LanguageRegistry {
     specs: Vec<LanguageSpec>,
+    // First language listing each extension; the fallback when no heuristic matches
     by_ext: HashMap<String, usize>,
+    // Extension -> languages with heuristics for it, in definition order
+    heuristics: HashMap<String, Vec<(usize, Vec<String>)>>,
     by_special: HashMap<String, usize>,
+    // Lowercase names and aliases
+    by_name: HashMap<String, usize>,
+    by_interpreter: HashMap<String, usize>,
     // Precomputed bytes per language index for fast access
-    line_markers_bytes: Vec<Vec<Vec<u8>>>,
-    block_markers_bytes: Vec<Option<(Vec<u8>, Vec<u8>)>>,
+    syntax: Vec<LanguageSyntax>,
 }
 
 impl LanguageRegistry {
-    fn from_specs(specs: Vec<LanguageSpec>) -> Self {
+    pub fn from_specs(specs: Vec<LanguageSpec>) -> Self {
         let mut by_ext = HashMap::new();
+        let mut heuristics: HashMap<String, Vec<(usize, Vec<String>)>> = HashMap::new();
         let mut by_special = HashMap::new();
-        let mut line_markers_bytes = Vec::with_capacity(specs.len());
-        let mut block_markers_bytes = Vec::with_capacity(specs.len());
+        let mut by_name = HashMap::new();
+        let mut by_interpreter = HashMap::new();
+        let mut syntax = Vec::with_capacity(specs.len());
         for (i, spec) in specs.iter().enumerate() {
             for ext in &spec.extensions {
-                by_ext.insert(ext.to_ascii_lowercase(), i);
+                by_ext.entry(ext.to_ascii_lowercase()).or_insert(i);
+            }
+            for (ext, tokens) in &spec.heuristics {
+                heuristics
+                    .entry(ext.to_ascii_lowercase())
+                    .or_default()
+                    .push((i, tokens.clone()));
             }
             for name in &spec.special_filenames {
                 by_special.insert(name.to_ascii_lowercase(), i);
             }
-            line_markers_bytes.push(
-                spec.line_markers
-                    .iter()
-                    .map(|s| s.as_bytes().to_vec())
-                    .collect(),
-            );
-            block_markers_bytes.push(
-                spec.block_markers
-                    .as_ref()
-                    .map(|(a, b)| (a.as_bytes().to_vec(), b.as_bytes().to_vec())),
-            );
+            for name in std::iter::once(&spec.name).chain(&spec.aliases) {
+                by_name.entry(name.to_ascii_lowercase()).or_insert(i);
+            }
+            for name in &spec.interpreters {
+                by_interpreter.insert(name.clone(), i);
+            }
+            syntax.push(LanguageSyntax::from_spec(spec));
         }
         Self {
             specs,
             by_ext,
+            heuristics,
             by_special,
-            line_markers_bytes,
-            block_markers_bytes,
+            by_name,
+            by_interpreter,
+            syntax,
         }
     }
-}
-
-static EMBEDDED_LANG_JSON: &str = include_str!("../assets/languages.json");
 
-pub static REGISTRY: Lazy<LanguageRegistry> = Lazy::new(|| {
-    let specs: Vec<LanguageSpec> =
-        serde_json::from_str(EMBEDDED_LANG_JSON).expect("invalid embedded languages.json");
-    LanguageRegistry::from_specs(specs)
-});
+    /// The built-in registry from `assets/languages.json`.
+    pub fn builtin() -> Arc<LanguageRegistry> {
+        BUILTIN_REGISTRY.clone()
+    }
 
-pub fn language_registry() -> &'static [LanguageSpec] {
-    &REGISTRY.specs
-}
+    pub fn specs(&self) -> &[LanguageSpec] {
+        &self.specs
+    }
 
-pub fn find_language_for_path(path: &Path) -> Option<&'static str> {
-    // 1) Special filenames (take precedence over extension)
-    if let Some(fname) = path.file_name().and_then(|s| s.to_str()) {
-        let lower = fname.to_ascii_lowercase();
-        if let Some(&idx) = REGISTRY.by_special.get(&lower) {
-            return Some(&language_registry()[idx].name);
-        }
-        match lower.as_str() {
-            "makefile" => return Some("Make"),
-            "dockerfile" => return Some("Dockerfile"),
-            "cmakelists.txt" => return Some("CMake"),
-            _ => {}
-        }
+    pub fn syntax(&self, idx: usize) -> &LanguageSyntax {
+        &self.syntax[idx]
     }
 
-    // 2) By extension
-    if let Some(ext) = path.extension().and_then(|s| s.to_str()) {
-        let ext = ext.to_ascii_lowercase();
-        if let Some(&idx) = REGISTRY.by_ext.get(&ext) {
-            return Some(&language_registry()[idx].name);
+    /// Language of `path`, trying in order:
+    ///
+    /// 1. Special filenames (`Dockerfile`, `Makefile`, `go.mod`, ...)
+    /// 2. Modelines (Emacs `-*-` on the first line, Vim in the first or last few lines,
+    ///    an Emacs `Local Variables:` block at the end), for files without an
+    ///    extension or with a template extension (`.in`, `.tmpl`)
+    /// 3. The extension, with content heuristics when several languages share it
+    /// 4. The shebang (`interpreters`), for files without a known extension
+    ///
+    /// `content` is the file's bytes when already in memory (all of them, or at least the
+    /// start); otherwise the start and end of `path` are read as needed.
+    pub fn detect(&self, path: &Path, content: Option<&[u8]>) -> Option<Detection> {
+        let found = |index, source| Some(Detection { index, source });
+        let head_cell = OnceCell::new();
+        let head = || -> Option<&[u8]> {
+            head_cell
+                .get_or_init(|| match content {
+                    Some(bytes) => Some(Cow::Borrowed(
+                        &bytes[..bytes.len().min(DETECTION_SAMPLE_LEN)],
+                    )),
+                    None => read_sample(path).map(Cow::Owned),
+                })
+                .as_deref()
+        };
+        let ext = path
+            .extension()
+            .and_then(|s| s.to_str())
+            .map(|s| s.to_ascii_lowercase());
+
+        // 1) Special filenames (take precedence over everything else)
+        if let Some(idx) = self.find_index_by_filename(path) {
+            return found(idx, DetectionSource::Filename);
         }
-    }
 
-    // 3) Shebang detection for scripts without extension
-    if path.extension().is_none()
-        && let Ok(f) = File::open(path)
-    {
-        let mut rdr = BufReader::new(f);
-        let mut first = String::new();
-        if rdr.read_line(&mut first).is_ok()
-            && let Some(lang) = parse_shebang(&first)
+        // 2) Modelines, where the name says little about the language
+        if ext
+            .as_deref()
+            .is_none_or(|e| MODELINE_EXTENSIONS.contains(&e))
+            && let Some(head) = head()
         {
-            return Some(lang);
+            let tail = match content {
+                Some(bytes) => Some(Cow::Borrowed(
+                    &bytes[bytes.len().saturating_sub(DETECTION_SAMPLE_LEN)..],
+                )),
+                None => read_tail(path).map(Cow::Owned),
+            };
+            let tail = tail.as_deref().unwrap_or(head);
+            // Emacs: `-*-` on the first line, or the second after a shebang
+            let mut lines = head.split(|&b| b == b'\n');
+            let emacs_line = match lines.next() {
+                Some(first) if first.starts_with(b"#!") => lines.next(),
+                first => first,
+            };
+            let lookup = |mode: String| self.find_index_by_name(&mode);
+            let emacs = || {
+                emacs_line
+                    .and_then(|l| parse_emacs_modeline(&String::from_utf8_lossy(l)))
+                    .and_then(lookup)
+            };
+            // Vim: any of the first or last few lines
+            let vim = || {
+                let first = head.split(|&b| b == b'\n').take(MODELINE_LINES);
+                let last = tail.rsplit(|&b| b == b'\n').take(MODELINE_LINES + 1);
+                first.chain(last).find_map(|line| {
+                    parse_vim_modeline(&String::from_utf8_lossy(line)).and_then(lookup)
+                })
+            };
+            // Emacs again: a `Local Variables:` block at the end
+            let local_vars =
+                || parse_emacs_local_variables(&String::from_utf8_lossy(tail)).and_then(lookup);
+            if let Some(idx) = emacs().or_else(vim).or_else(local_vars) {
+                return found(idx, DetectionSource::Modeline);
+            }
         }
-    }
-    None
-}
 
-pub fn find_language_index_for_path(path: &Path) -> Option<usize> {
-    if let Some(fname) = path.file_name().and_then(|s| s.to_str()) {
-        let lower = fname.to_ascii_lowercase();
-        if let Some(&idx) = REGISTRY.by_special.get(&lower) {
-            return Some(idx);
-        }
-        match lower.as_str() {
-            "makefile" => return language_registry().iter().position(|l| l.name == "Make"),
-            "dockerfile" => {
-                return language_registry()
+        // 3) By extension, letting heuristics pick among languages sharing it
+        if let Some(ext) = &ext {
+            if let Some(candidates) = self.heuristics.get(ext)
+                && let Some(sample) = head()
+                && let Some((idx, _)) = candidates
                     .iter()
-                    .position(|l| l.name == "Dockerfile");
+                    .find(|(_, tokens)| matches_heuristic(sample, tokens))
+            {
+                return found(*idx, DetectionSource::Heuristic);
+            }
+            if let Some(&idx) = self.by_ext.get(ext) {
+                return found(idx, DetectionSource::Extension);
             }
-            "cmakelists.txt" => return language_registry().iter().position(|l| l.name == "CMake"),
-            _ => {}
         }
+
+        // 4) Shebang, for scripts without a known extension
+        let sample = head()?;
+        let line = sample.split(|&b| b == b'\n').next().unwrap_or_default();
+        let idx = shebang_interpreter(&String::from_utf8_lossy(line))
+            .and_then(|cmd| self.find_index_by_interpreter(cmd))?;
+        found(idx, DetectionSource::Shebang)
     }
-    if let Some(ext) = path.extension().and_then(|s| s.to_str()) {
-        let ext = ext.to_ascii_lowercase();
-        if let Some(&idx) = REGISTRY.by_ext.get(&ext) {
+
+    fn find_index_by_filename(&self, path: &Path) -> Option<usize> {
+        let lower = path.file_name()?.to_str()?.to_ascii_lowercase();
+        if let Some(&idx) = self.by_special.get(&lower) {
             return Some(idx);
         }
+        match lower.as_str() {
+            "makefile" => self.find_index_by_name("Make"),
+            "dockerfile" => self.find_index_by_name("Dockerfile"),
+            "cmakelists.txt" => self.find_index_by_name("CMake"),
+            _ => None,
+        }
     }
-    if path.extension().is_none()
-        && let Ok(f) = File::open(path)
-    {
-        let mut rdr = BufReader::new(f);
-        let mut first = String::new();
-        if rdr.read_line(&mut first).is_ok()
-            && let Some(lang) = parse_shebang(&first)
-        {
-            return language_registry().iter().position(|l| l.name == lang);
+
+    /// The language `detect` would pick for `path` when that does not depend on the
+    /// content: a special filename, or an extension without heuristics or modelines.
+    pub fn detect_by_name(&self, path: &Path) -> Option<Detection> {
+        if let Some(index) = self.find_index_by_filename(path) {
+            return Some(Detection {
+                index,
+                source: DetectionSource::Filename,
+            });
+        }
+        let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+        if MODELINE_EXTENSIONS.contains(&ext.as_str()) || self.heuristics.contains_key(&ext) {
+            return None;
         }
+        let index = *self.by_ext.get(&ext)?;
+        Some(Detection {
+            index,
+            source: DetectionSource::Extension,
+        })
+    }
+
+    /// Look up a language by shebang interpreter, ignoring a version suffix
+    /// (`python3.12`, `lua5.4`, `guile-3.0`) when the exact name is not listed.
+    pub fn find_index_by_interpreter(&self, name: &str) -> Option<usize> {
+        self.by_interpreter
+            .get(name)
+            .or_else(|| {
+                let base = name
+                    .trim_end_matches(|c: char| c.is_ascii_digit() || c == '.')
+                    .trim_end_matches('-');
+                self.by_interpreter.get(base)
+            })
+            .copied()
+    }
+
+    pub fn find_index_for_path(&self, path: &Path) -> Option<usize> {
+        self.detect(path, None).map(|d| d.index)
+    }
+
+    pub fn find_for_path(&self, path: &Path) -> Option<&str> {
+        self.find_index_for_path(path)
+            .map(|idx| self.specs[idx].name.as_str())
+    }
+
+    /// Look up a language by name, alias or extension, case-insensitively
+    /// (e.g. `rust`, `bash`, `ts`), with hyphens standing for spaces (`emacs-lisp`). Used
+    /// for fence info strings, `lang` attributes, modelines and `linguist-language`.
+    pub fn find_index_by_name(&self, name: &str) -> Option<usize> {
+        let lower = name.trim().trim_start_matches('.').to_ascii_lowercase();
+        self.by_name
+            .get(&lower)
+            .or_else(|| self.by_ext.get(&lower))
+            .or_else(|| self.by_name.get(&lower.replace('-', " ")))
+            .copied()
     }
-    None
 }
 
-pub type LanguageMarkersBytes = (&'static [Vec<u8>], Option<(&'static [u8], &'static [u8])>);
+impl std::fmt::Debug for LanguageRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LanguageRegistry")
+            .field("languages", &self.specs.len())
+            .finish_non_exhaustive()
+    }
+}
 
-pub fn language_markers_bytes(idx: usize) -> LanguageMarkersBytes {
-    let lines: &'static [Vec<u8>] = &REGISTRY.line_markers_bytes[idx];
-    let blocks = REGISTRY.block_markers_bytes[idx]
-        .as_ref()
-        .map(|(a, b)| (a.as_slice(), b.as_slice()));
-    (lines, blocks)
+impl std::ops::Index<usize> for LanguageRegistry {
+    type Output = LanguageSpec;
+
+    fn index(&self, idx: usize) -> &LanguageSpec {
+        &self.specs[idx]
+    }
 }
 
-fn parse_shebang(line: &str) -> Option<&'static str> {
-    let s = line.trim_start();
-    if !s.starts_with("#!") {
-        return None;
+static EMBEDDED_LANG_JSON: &str = include_str!("../assets/languages.json");
+
+/// Language definitions shipped with ocloc.
+pub fn builtin_specs() -> Vec<LanguageSpec> {
+    serde_json::from_str(EMBEDDED_LANG_JSON).expect("invalid embedded languages.json")
+}
+
+// Shared by `LanguageRegistry::builtin` and the free lookup functions below
+static BUILTIN_REGISTRY: Lazy<Arc<LanguageRegistry>> =
+    Lazy::new(|| Arc::new(LanguageRegistry::from_specs(builtin_specs())));
+
+/// An extension or special filename that moved to another language while merging
+/// user definitions into a registry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LanguageConflict {
+    /// `.ext` for extensions, the file name for special filenames
+    pub key: String,
+    pub previous: String,
+    pub language: String,
+    /// File the winning definition came from
+    pub source: String,
+}
+
+impl std::fmt::Display for LanguageConflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} is claimed by both {} and {} (from {}); using {}",
+            self.key, self.previous, self.language, self.source, self.language
+        )
     }
-    let s = s[2..].trim();
-    // handle /usr/bin/env pattern
-    let tokens: Vec<&str> = s.split_whitespace().collect();
-    if tokens.is_empty() {
-        return None;
+}
+
+/// Builds a registry from the built-in languages plus user definitions. A definition
+/// whose name matches an existing language (case-insensitively) replaces it; otherwise
+/// it is added. Extensions and special filenames claimed by a later definition are
+/// taken away from earlier languages and recorded as conflicts, unless the definition
+/// declares `heuristics` for that extension, in which case the languages share it.
+#[derive(Debug, Clone)]
+pub struct RegistryBuilder {
+    specs: Vec<LanguageSpec>,
+    conflicts: Vec<LanguageConflict>,
+}
+
+impl Default for RegistryBuilder {
+    fn default() -> Self {
+        Self::from_specs(builtin_specs())
+    }
+}
+
+impl RegistryBuilder {
+    /// Start from `specs` instead of the built-in languages.
+    pub fn from_specs(specs: Vec<LanguageSpec>) -> Self {
+        Self {
+            specs,
+            conflicts: Vec::new(),
+        }
     }
-    let cmd = if tokens[0].ends_with("env") && tokens.len() > 1 {
-        tokens[1]
-    } else {
-        tokens[0]
-    };
-    let cmd_lower = cmd.to_ascii_lowercase();
-    if cmd_lower.contains("python") {
-        return Some("Python");
+
+    /// Merge definitions from a JSON file holding a list of languages (the
+    /// `assets/languages.json` schema).
+    pub fn add_file(&mut self, path: &Path) -> Result<&mut Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("read language file: {}", path.display()))?;
+        let specs: Vec<LanguageSpec> = serde_json::from_str(&text)
+            .with_context(|| format!("parse language file: {}", path.display()))?;
+        self.add_specs(specs, &path.display().to_string())
     }
-    if cmd_lower.contains("bash")
-        || cmd_lower == "sh"
-        || cmd_lower.contains("zsh")
-        || cmd_lower.contains("ksh")
-        || cmd_lower.contains("fish")
-    {
-        return Some("Shell");
+
+    /// Merge `specs`; `source` names their origin in conflict reports.
+    pub fn add_specs(&mut self, specs: Vec<LanguageSpec>, source: &str) -> Result<&mut Self> {
+        for spec in specs {
+            if spec.name.trim().is_empty() {
+                bail!("language without a name in {}", source);
+            }
+            let existing = self
+                .specs
+                .iter()
+                .position(|s| s.name.eq_ignore_ascii_case(&spec.name));
+            for other in self
+                .specs
+                .iter_mut()
+                .enumerate()
+                .filter(|&(i, _)| Some(i) != existing)
+                .map(|(_, s)| s)
+            {
+                for (ext, claimed) in [(true, &spec.extensions), (false, &spec.special_filenames)] {
+                    let list = if ext {
+                        &mut other.extensions
+                    } else {
+                        &mut other.special_filenames
+                    };
+                    list.retain(|key| {
+                        // With a heuristic for the extension, the new language shares it
+                        let shared =
+                            ext && spec.heuristics.keys().any(|h| h.eq_ignore_ascii_case(key));
+                        let taken = !shared && claimed.iter().any(|c| c.eq_ignore_ascii_case(key));
+                        if taken {
+                            self.conflicts.push(LanguageConflict {
+                                key: if ext { format!(".{key}") } else { key.clone() },
+                                previous: other.name.clone(),
+                                language: spec.name.clone(),
+                                source: source.to_string(),
+                            });
+                        }
+                        !taken
+                    });
+                }
+                let exts = &other.extensions;
+                other
+                    .heuristics
+                    .retain(|h, _| exts.iter().any(|e| e.eq_ignore_ascii_case(h)));
+            }
+            match existing {
+                Some(i) => self.specs[i] = spec,
+                None => self.specs.push(spec),
+            }
+        }
+        Ok(self)
     }
-    if cmd_lower.contains("node") || cmd_lower.contains("deno") {
-        return Some("JavaScript");
+
+    /// Extensions and filenames reassigned so far.
+    pub fn conflicts(&self) -> &[LanguageConflict] {
+        &self.conflicts
+    }
+
+    pub fn build(self) -> LanguageRegistry {
+        LanguageRegistry::from_specs(self.specs)
+    }
+}
+
+/// Per-user language definitions: `$XDG_CONFIG_HOME/ocloc/languages.json`, falling back
+/// to `~/.config/ocloc/languages.json`.
+pub fn user_languages_path() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".config")))?;
+    Some(base.join("ocloc").join("languages.json"))
+}
+
+// Lookups in the built-in languages only; a run with user definitions goes through
+// its own `LanguageRegistry`.
+
+pub fn language_registry() -> &'static [LanguageSpec] {
+    &BUILTIN_REGISTRY.specs
+}
+
+pub fn find_language_for_path(path: &Path) -> Option<&'static str> {
+    BUILTIN_REGISTRY.find_for_path(path)
+}
+
+pub fn find_language_index_for_path(path: &Path) -> Option<usize> {
+    BUILTIN_REGISTRY.find_index_for_path(path)
+}
+
+/// Look up a built-in language by name, alias or extension; see
+/// [`LanguageRegistry::find_index_by_name`].
+pub fn find_language_index_by_name(name: &str) -> Option<usize> {
+    BUILTIN_REGISTRY.find_index_by_name(name)
+}
+
+pub type LanguageMarkersBytes = (&'static [Vec<u8>], &'static [(Vec<u8>, Vec<u8>)]);
+
+pub fn language_markers_bytes(idx: usize) -> LanguageMarkersBytes {
+    let syntax = language_syntax(idx);
+    (&syntax.line_markers, &syntax.block_markers)
+}
+
+pub fn language_syntax(idx: usize) -> &'static LanguageSyntax {
+    BUILTIN_REGISTRY.syntax(idx)
+}
+
+// Leading bytes of a file for heuristics, modelines and shebangs
+fn read_sample(path: &Path) -> Option<Vec<u8>> {
+    let mut buf = Vec::new();
+    File::open(path)
+        .ok()?
+        .take(DETECTION_SAMPLE_LEN as u64)
+        .read_to_end(&mut buf)
+        .ok()?;
+    Some(buf)
+}
+
+// Trailing bytes of a file for modelines; `None` if the leading sample already covers it
+fn read_tail(path: &Path) -> Option<Vec<u8>> {
+    let mut file = File::open(path).ok()?;
+    let len = file.metadata().ok()?.len();
+    if len <= DETECTION_SAMPLE_LEN as u64 {
+        return None;
     }
-    if cmd_lower.contains("perl") {
-        return Some("Perl");
+    file.seek(SeekFrom::End(-(DETECTION_SAMPLE_LEN as i64)))
+        .ok()?;
+    let mut buf = Vec::new();
+    file.read_to_end(&mut buf).ok()?;
+    Some(buf)
+}
+
+/// Language named by an Emacs `-*-` line (`-*- mode: python -*-`, `-*- ruby -*-`). Emacs
+/// only reads it from the first line, or the second after a shebang.
+fn parse_emacs_modeline(line: &str) -> Option<String> {
+    let start = line.find("-*-")?;
+    let rest = &line[start + 3..];
+    let vars = &rest[..rest.find("-*-")?];
+    if !vars.contains(':') {
+        return Some(vars.trim().to_string()).filter(|m| !m.is_empty());
     }
-    if cmd_lower.contains("ruby") {
-        return Some("Ruby");
+    vars.split(';').find_map(|var| {
+        let (key, value) = var.split_once(':')?;
+        key.trim()
+            .eq_ignore_ascii_case("mode")
+            .then(|| value.trim().to_string())
+    })
+}
+
+/// Language named by a Vim modeline (`vim: set ft=yaml:`, `vi: filetype=sh`).
+fn parse_vim_modeline(line: &str) -> Option<String> {
+    // `vim:`, `vi:` or `ex:` at the start of the line or after whitespace
+    let rest = ["vim:", "vi:", "ex:"].iter().find_map(|marker| {
+        line.match_indices(marker).find_map(|(i, _)| {
+            let boundary = line[..i]
+                .chars()
+                .next_back()
+                .is_none_or(char::is_whitespace);
+            boundary.then(|| &line[i + marker.len()..])
+        })
+    })?;
+    rest.split(|c: char| c == ':' || c.is_whitespace())
+        .find_map(|opt| match opt.split_once('=') {
+            Some(("ft" | "filetype" | "syn" | "syntax", value)) if !value.is_empty() => {
+                Some(value.to_string())
+            }
+            _ => None,
+        })
+}
+
+/// `mode` of an Emacs `Local Variables:` block near the end of a file. Each line of the
+/// block repeats the prefix (e.g. a comment marker) before `Local Variables:`.
+fn parse_emacs_local_variables(tail: &str) -> Option<String> {
+    let start = tail.rfind("Local Variables:")?;
+    let line_start = tail[..start].rfind('\n').map_or(0, |i| i + 1);
+    let prefix = tail[line_start..start].trim();
+    tail[start..].lines().skip(1).find_map(|line| {
+        let var = line.trim().strip_prefix(prefix)?.trim();
+        if var.starts_with("End:") {
+            // Stop at the end of the block: nothing after it counts
+            return Some(None);
+        }
+        let (key, value) = var.split_once(':')?;
+        key.trim()
+            .eq_ignore_ascii_case("mode")
+            .then(|| value.split_whitespace().next().map(str::to_string))
+    })?
+}
+
+/// Whether `sample` contains any of `tokens`; tokens starting with `^` only match at the
+/// start of a line, ignoring indentation.
+fn matches_heuristic(sample: &[u8], tokens: &[String]) -> bool {
+    tokens.iter().any(|token| match token.strip_prefix('^') {
+        Some(anchored) => sample
+            .split(|&b| b == b'\n')
+            .any(|line| line.trim_ascii_start().starts_with(anchored.as_bytes())),
+        None => memchr::memmem::find(sample, token.as_bytes()).is_some(),
+    })
+}
+
+/// Interpreter named by a shebang line: the program's file name, or for `env` the
+/// command after its options and variable assignments (`env -S deno run`, `env -i A=1 lua`).
+fn shebang_interpreter(line: &str) -> Option<&str> {
+    let mut tokens = line.trim_start().strip_prefix("#!")?.split_whitespace();
+    let program = tokens.next()?.rsplit('/').next()?;
+    if program != "env" {
+        return Some(program);
     }
-    if cmd_lower.contains("php") {
-        return Some("PHP");
+    while let Some(token) = tokens.next() {
+        match token {
+            // Options taking a separate value
+            "-u" | "--unset" | "-C" | "--chdir" => {
+                tokens.next();
+            }
+            // The split string's words follow as separate tokens
+            "-S" | "--split-string" => {}
+            _ if token.starts_with("--split-string=") => {
+                return Some(&token["--split-string=".len()..]).filter(|t| !t.is_empty());
+            }
+            _ if token.starts_with("-S") => return Some(&token[2..]),
+            _ if token.starts_with('-') || token.contains('=') => {}
+            _ => return token.rsplit('/').next(),
+        }
     }
     None
 }
@@ -244,6 +930,68 @@ mod tests {
         assert_eq!(find_language_for_path(&sh), Some("Shell"));
     }
 
+    #[test]
+    fn detects_shebang_interpreters_listed_in_json() {
+        let dir = tempdir().unwrap();
+        for (name, line, lang) in [
+            ("tool", "#!/usr/bin/env tclsh", "Tcl"),
+            ("bootstrap", "#!/usr/local/bin/lua5.4", "Lua"),
+            ("report", "#!/usr/bin/env Rscript", "R"),
+            ("prompt", "#!/usr/bin/fish", "Fish"),
+        ] {
+            let path = dir.path().join(name);
+            std::fs::write(&path, format!("{line}\n")).unwrap();
+            assert_eq!(find_language_for_path(&path), Some(lang), "{line}");
+        }
+    }
+
+    #[test]
+    fn parses_env_options_and_versioned_interpreters() {
+        let registry = LanguageRegistry::builtin();
+        let cases = [
+            ("#!/usr/bin/env -S deno run --allow-net", "JavaScript"),
+            ("#!/usr/bin/env -Sperl -w", "Perl"),
+            ("#!/usr/bin/env --split-string=ruby", "Ruby"),
+            ("#!/usr/bin/env -i PATH=/bin python3.12", "Python"),
+            ("#!/usr/bin/env -u HOME lua5.4", "Lua"),
+            ("#!/usr/bin/env Rscript --vanilla", "R"),
+            ("#!/usr/bin/tclsh8.6", "Tcl"),
+            ("#!/usr/bin/awk -f", "AWK"),
+            ("#!/usr/bin/env pwsh", "PowerShell"),
+            ("#!/usr/bin/env perl6", "Raku"),
+            ("#! /bin/bash", "Shell"),
+        ];
+        for (line, lang) in cases {
+            let content = format!("{line}\n");
+            let d = registry
+                .detect(Path::new("script"), Some(content.as_bytes()))
+                .unwrap_or_else(|| panic!("{line}"));
+            assert_eq!(registry[d.index].name, lang, "{line}");
+            assert_eq!(d.source, DetectionSource::Shebang);
+        }
+        assert_eq!(
+            registry.detect(Path::new("script"), Some(b"#!/usr/bin/env -i\n")),
+            None
+        );
+    }
+
+    #[test]
+    fn shebangs_apply_to_unknown_extensions_only() {
+        let registry = LanguageRegistry::builtin();
+        let name = |path: &str| {
+            let d = registry.detect(Path::new(path), Some(b"#!/bin/sh\necho hi\n"))?;
+            Some((registry[d.index].name.as_str(), d.source))
+        };
+        assert_eq!(
+            name("deploy.sample"),
+            Some(("Shell", DetectionSource::Shebang))
+        );
+        assert_eq!(
+            name("notes.txt"),
+            Some(("Text", DetectionSource::Extension))
+        );
+    }
+
     #[test]
     fn detects_doc_and_config_types() {
         let dir = tempdir().unwrap();
@@ -302,6 +1050,301 @@ mod tests {
         assert_eq!(find_language_for_path(&readme), Some("Text"));
     }
 
+    #[test]
+    fn block_markers_accept_single_pair_or_list() {
+        let specs: Vec<LanguageSpec> = serde_json::from_str(
+            r#"[
+                {"name": "A", "extensions": [], "line_markers": [], "block_markers": null},
+                {"name": "B", "extensions": [], "line_markers": [], "block_markers": ["/*", "*/"]},
+                {"name": "C", "extensions": [], "line_markers": [],
+                 "block_markers": [["{", "}"], ["(*", "*)"]]},
+                {"name": "D", "extensions": [], "line_markers": []}
+            ]"#,
+        )
+        .unwrap();
+        assert!(specs[0].block_markers.is_empty());
+        assert_eq!(
+            specs[1].block_markers,
+            vec![("/*".to_string(), "*/".to_string())]
+        );
+        assert_eq!(specs[2].block_markers.len(), 2);
+        assert!(specs[3].block_markers.is_empty());
+    }
+
+    #[test]
+    fn nested_blocks_accept_flag_or_openers() {
+        let specs: Vec<LanguageSpec> = serde_json::from_str(
+            r#"[
+                {"name": "A", "block_markers": ["/*", "*/"], "nested_blocks": true},
+                {"name": "B", "block_markers": [["/+", "+/"], ["/*", "*/"]],
+                 "doc_block_markers": [["/++", "+/"], ["/**", "*/"]], "nested_blocks": ["/+"]},
+                {"name": "C", "block_markers": ["/*", "*/"]}
+            ]"#,
+        )
+        .unwrap();
+        let syntax: Vec<LanguageSyntax> = specs.iter().map(LanguageSyntax::from_spec).collect();
+        assert_eq!(syntax[0].block_nesting, vec![Some(b"/*".to_vec())]);
+        assert_eq!(syntax[1].block_nesting, vec![Some(b"/+".to_vec()), None]);
+        // Doc pairs nest like the ordinary pair with the same closer
+        assert_eq!(
+            syntax[1].doc_block_nesting,
+            vec![Some(b"/+".to_vec()), None]
+        );
+        assert_eq!(syntax[2].block_nesting, vec![None]);
+    }
+
+    #[test]
+    fn registry_builder_overrides_and_reports_conflicts() {
+        let user: Vec<LanguageSpec> = serde_json::from_str(
+            r#"[
+  {"name": "javascript", "extensions": ["js"], "line_markers": [";"]},
+  {"name": "Pipeline", "extensions": ["pipeline", "H"], "special_filenames": ["Makefile"]}
+]"#,
+        )
+        .unwrap();
+        let mut builder = RegistryBuilder::default();
+        builder.add_specs(user, "user.json").unwrap();
+        let keys: Vec<_> = builder
+            .conflicts()
+            .iter()
+            .map(|c| (c.key.as_str(), c.previous.as_str(), c.language.as_str()))
+            .collect();
+        assert_eq!(
+            keys,
+            [
+                (".h", "C", "Pipeline"),
+                (".h", "C++", "Pipeline"),
+                ("makefile", "Make", "Pipeline"),
+                (".h", "Objective-C", "Pipeline"),
+            ]
+        );
+
+        let registry = builder.build();
+        let builtin = LanguageRegistry::builtin();
+        assert_eq!(registry.specs().len(), builtin.specs().len() + 1);
+        // Same name replaces the built-in definition, dropping its other extensions
+        let js = registry.find_index_for_path(Path::new("a.js")).unwrap();
+        assert_eq!(registry[js].line_markers, [";"]);
+        assert_eq!(registry.find_index_for_path(Path::new("a.jsx")), None);
+        assert_eq!(registry.find_for_path(Path::new("x.h")), Some("Pipeline"));
+        assert_eq!(
+            registry.find_for_path(Path::new("Makefile")),
+            Some("Pipeline")
+        );
+        assert_eq!(registry.find_for_path(Path::new("x.c")), Some("C"));
+        // Languages that lost `.h` no longer claim it through heuristics
+        let cpp = registry.detect(Path::new("x.h"), Some(b"class A {};\n"));
+        assert_eq!(
+            cpp.map(|d| registry[d.index].name.as_str()),
+            Some("Pipeline")
+        );
+        // The built-in registry is unaffected
+        assert_eq!(builtin.find_for_path(Path::new("x.h")), Some("C"));
+    }
+
+    #[test]
+    fn parses_vim_and_emacs_modelines() {
+        let vim = [
+            ("# vim: set ft=yaml:", Some("yaml")),
+            ("// vim:ft=javascript ts=2", Some("javascript")),
+            ("/* vi: set filetype=sh : */", Some("sh")),
+            ("# ex: syntax=python", Some("python")),
+            ("regex: ft=yaml", None),
+            ("# vim: ts=4 sw=4", None),
+        ];
+        for (line, mode) in vim {
+            assert_eq!(parse_vim_modeline(line).as_deref(), mode, "{line}");
+        }
+        let emacs = [
+            ("# -*- mode: python -*-", Some("python")),
+            ("// -*- Mode: C++; tab-width: 4 -*-", Some("C++")),
+            (";; -*- emacs-lisp -*-", Some("emacs-lisp")),
+            ("# -*- coding: utf-8 -*-", None),
+        ];
+        for (line, mode) in emacs {
+            assert_eq!(parse_emacs_modeline(line).as_deref(), mode, "{line}");
+        }
+        let local_vars = "x\n# Local Variables:\n# fill-column: 80\n# mode: ruby\n# End:\n";
+        assert_eq!(
+            parse_emacs_local_variables(local_vars).as_deref(),
+            Some("ruby")
+        );
+        let after_end = "# Local Variables:\n# End:\n# mode: ruby\n";
+        assert_eq!(parse_emacs_local_variables(after_end), None);
+    }
+
+    #[test]
+    fn modelines_override_extensionless_and_template_files() {
+        let dir = tempdir().unwrap();
+        let registry = LanguageRegistry::builtin();
+        let detect = |name: &str, content: &str| {
+            let path = dir.path().join(name);
+            std::fs::write(&path, content).unwrap();
+            let d = registry.detect(&path, None).unwrap();
+            (registry[d.index].name.as_str(), d.source)
+        };
+        assert_eq!(
+            detect("deploy", "# vim: set ft=yaml:\nkey: 1\n"),
+            ("YAML", DetectionSource::Modeline)
+        );
+        assert_eq!(
+            detect("setup.cfg.in", "# -*- mode: python -*-\nx = 1\n"),
+            ("Python", DetectionSource::Modeline)
+        );
+        // At the end of a file longer than the leading sample
+        let long = format!("{}<!-- vim: ft=html -->\n", "<p>text</p>\n".repeat(2000));
+        assert_eq!(
+            detect("page.tmpl", &long),
+            ("HTML", DetectionSource::Modeline)
+        );
+        // Emacs reads `-*-` from the first line, or the second after a shebang, only
+        assert_eq!(
+            detect("script", "#!/bin/sh\n# -*- mode: ruby -*-\nputs 1\n"),
+            ("Ruby", DetectionSource::Modeline)
+        );
+        assert_eq!(
+            detect(
+                "late",
+                "#!/bin/sh\n\necho 1\n# -*- mode: ruby -*-\necho 2\n"
+            ),
+            ("Shell", DetectionSource::Shebang)
+        );
+        assert_eq!(
+            detect("vars", "puts 1\n# Local Variables:\n# mode: ruby\n# End:\n"),
+            ("Ruby", DetectionSource::Modeline)
+        );
+        // Modelines beat shebangs but not real extensions
+        assert_eq!(
+            detect("tool", "#!/bin/sh\n# vim: ft=python\n"),
+            ("Python", DetectionSource::Modeline)
+        );
+        assert_eq!(
+            detect("app.py", "# vim: ft=ruby\n"),
+            ("Python", DetectionSource::Extension)
+        );
+    }
+
+    #[test]
+    fn ambiguous_extensions_use_content_heuristics() {
+        let registry = LanguageRegistry::builtin();
+        let cases: [(&str, &str, &str, DetectionSource); 15] = [
+            (
+                "a.h",
+                "int add(int a, int b);\n",
+                "C",
+                DetectionSource::Extension,
+            ),
+            (
+                "a.h",
+                "#pragma once\nnamespace app {\n",
+                "C++",
+                DetectionSource::Heuristic,
+            ),
+            (
+                "a.h",
+                "#import <Foundation/Foundation.h>\n",
+                "Objective-C",
+                DetectionSource::Heuristic,
+            ),
+            (
+                "a.m",
+                "@implementation Foo\n@end\n",
+                "Objective-C",
+                DetectionSource::Heuristic,
+            ),
+            (
+                "a.m",
+                "function y = f(x)\n  y = x;\nend\n",
+                "MATLAB",
+                DetectionSource::Heuristic,
+            ),
+            (
+                "a.pl",
+                "use strict;\nmy $x = 1;\n",
+                "Perl",
+                DetectionSource::Extension,
+            ),
+            // Tokens are anchored: a mention in a comment or string does not count
+            (
+                "a.h",
+                "/* unlike std::vector */\nint add(int a, int b);\n",
+                "C",
+                DetectionSource::Extension,
+            ),
+            (
+                "a.pl",
+                "print \"a :- b\\n\";\n",
+                "Perl",
+                DetectionSource::Extension,
+            ),
+            (
+                "a.pro",
+                "count(N) :- N > 0.\n% N += 1 is not QMake\n",
+                "Prolog",
+                DetectionSource::Heuristic,
+            ),
+            (
+                "a.pl",
+                "parent(X, Y) :- father(X, Y).\n",
+                "Prolog",
+                DetectionSource::Heuristic,
+            ),
+            (
+                "a.v",
+                "module top(input clk);\nendmodule\n",
+                "Verilog",
+                DetectionSource::Extension,
+            ),
+            (
+                "a.v",
+                "Require Import Arith.\nLemma x : True.\n",
+                "Coq",
+                DetectionSource::Heuristic,
+            ),
+            ("a.v", "fn main() {\n}\n", "V", DetectionSource::Heuristic),
+            (
+                "a.ts",
+                "const n: number = 1;\n",
+                "TypeScript",
+                DetectionSource::Extension,
+            ),
+            (
+                "a.ts",
+                "<?xml version=\"1.0\"?>\n<TS version=\"2.1\">\n",
+                "XML",
+                DetectionSource::Heuristic,
+            ),
+        ];
+        for (file, content, lang, source) in cases {
+            let d = registry
+                .detect(Path::new(file), Some(content.as_bytes()))
+                .unwrap();
+            assert_eq!(
+                (registry[d.index].name.as_str(), d.source),
+                (lang, source),
+                "{content}"
+            );
+        }
+    }
+
+    #[test]
+    fn user_heuristics_share_an_extension() {
+        let user: Vec<LanguageSpec> = serde_json::from_str(
+            r#"[{"name": "Hdl", "extensions": ["h"], "heuristics": {"h": ["^module "]}}]"#,
+        )
+        .unwrap();
+        let mut builder = RegistryBuilder::default();
+        builder.add_specs(user, "user.json").unwrap();
+        assert!(builder.conflicts().is_empty());
+        let registry = builder.build();
+        let name = |content: &[u8]| {
+            let d = registry.detect(Path::new("x.h"), Some(content)).unwrap();
+            registry[d.index].name.clone()
+        };
+        assert_eq!(name(b"module counter;\n"), "Hdl");
+        assert_eq!(name(b"int x;\n"), "C");
+    }
+
     #[test]
     fn languages_json_is_consistent() {
         use std::collections::HashSet;
@@ -309,15 +1352,33 @@ mod tests {
         let mut names = HashSet::new();
         let mut exts = HashSet::new();
         let mut specials = HashSet::new();
+        let mut aliases = HashSet::new();
+        let mut interpreters = HashSet::new();
         for s in specs {
+            for a in std::iter::once(&s.name).chain(&s.aliases) {
+                assert!(
+                    aliases.insert(a.to_ascii_lowercase()),
+                    "duplicate language name or alias: {}",
+                    a
+                );
+            }
             assert!(!s.name.trim().is_empty(), "language name must be non-empty");
             assert!(names.insert(&s.name), "duplicate language name: {}", s.name);
             for e in &s.extensions {
                 let norm = e.to_ascii_lowercase();
                 assert!(
-                    exts.insert(norm.clone()),
-                    "duplicate extension across languages: {}",
-                    norm
+                    exts.insert(norm.clone()) || s.heuristics.contains_key(&norm),
+                    "extension {} shared without a heuristic in {}",
+                    norm,
+                    s.name
+                );
+            }
+            for ext in s.heuristics.keys() {
+                assert!(
+                    s.extensions.contains(ext),
+                    "heuristic for .{} not listed in the extensions of {}",
+                    ext,
+                    s.name
                 );
             }
             for f in &s.special_filenames {
@@ -328,13 +1389,49 @@ mod tests {
                     norm
                 );
             }
-            if let Some((ref a, ref b)) = s.block_markers {
+            for i in &s.interpreters {
+                assert!(
+                    interpreters.insert(i.as_str()),
+                    "duplicate interpreter across languages: {}",
+                    i
+                );
+            }
+            for (a, b) in s.block_markers.iter().chain(&s.doc_block_markers) {
                 assert!(
                     !a.is_empty() && !b.is_empty(),
                     "block markers must be non-empty for {}",
                     s.name
                 );
             }
+            for m in &s.doc_string_markers {
+                assert!(
+                    s.quotes
+                        .iter()
+                        .chain(&s.verbatim_quotes)
+                        .any(|(q, _)| m.ends_with(q.as_str())),
+                    "doc string marker {} must end with a quote of {}",
+                    m,
+                    s.name
+                );
+            }
+            for (a, b) in s.quotes.iter().chain(&s.verbatim_quotes) {
+                assert!(
+                    !a.is_empty() && !b.is_empty(),
+                    "quote delimiters must be non-empty for {}",
+                    s.name
+                );
+            }
+            for m in &s.multiline_quotes {
+                assert!(
+                    s.quotes
+                        .iter()
+                        .chain(&s.verbatim_quotes)
+                        .any(|(q, _)| q == m),
+                    "multiline quote {} is not a quote of {}",
+                    m,
+                    s.name
+                );
+            }
         }
     }
 }
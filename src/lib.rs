@@ -1,8 +1,12 @@
 pub mod analyzer;
+pub mod cache;
 pub mod cli;
+pub mod codeowners;
 pub mod formatters;
 pub mod languages;
 pub mod traversal;
 pub mod types;
 pub mod types_diff;
 pub mod vcs;
+
+pub use analyzer::{Analyzer, AnalyzerBuilder, AnalyzerOptions, LineKind};
@@ -1,13 +1,16 @@
 use indexmap::IndexMap;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Copy, Default, Serialize)]
+use crate::languages::DetectionSource;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct FileCounts {
     pub files: usize,
     pub total: usize,
     pub code: usize,
     pub comment: usize,
     pub blank: usize,
+    pub doc: usize,
 }
 
 impl FileCounts {
@@ -24,6 +27,44 @@ impl FileCounts {
         self.code += other.code;
         self.comment += other.comment;
         self.blank += other.blank;
+        self.doc += other.doc;
+    }
+}
+
+/// Result of analyzing one file. Lines inside embedded regions (e.g. `<script>` in HTML,
+/// fenced code in Markdown) are attributed to their own language.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FileAnalysis {
+    /// Lines in the file's own language; `files` is 1
+    pub counts: FileCounts,
+    /// Embedded language name -> lines in that language; `files` is 0
+    pub embedded: IndexMap<String, FileCounts>,
+    /// Set when the file looks machine-generated rather than hand-written
+    pub generated: Option<GeneratedKind>,
+    /// Set when the content looks binary (NUL bytes near the start); nothing is counted
+    pub binary: bool,
+}
+
+/// Why a file was considered generated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GeneratedKind {
+    /// A marker such as `Code generated ... DO NOT EDIT.` or `@generated` near the top
+    Marker,
+    /// Mostly very long lines with little whitespace (minified bundles, encoded data)
+    Minified,
+    /// A package manager lockfile (`package-lock.json`, `Cargo.lock`, ...)
+    Lockfile,
+}
+
+impl FileAnalysis {
+    /// All lines of the file regardless of language.
+    pub fn combined(&self) -> FileCounts {
+        let mut all = self.counts;
+        for counts in self.embedded.values() {
+            all.merge(counts);
+        }
+        all
     }
 }
 
@@ -32,6 +73,22 @@ pub struct AnalyzeResult {
     #[serde(rename = "languages")]
     pub per_lang: IndexMap<String, FileCounts>,
     pub totals: FileCounts,
+    /// Parent language -> embedded language -> lines (also included in `per_lang`)
+    #[serde(skip_serializing_if = "IndexMap::is_empty")]
+    pub embedded: IndexMap<String, IndexMap<String, FileCounts>>,
+    /// Generated files by language, left out of `per_lang` and `totals`
+    #[serde(skip_serializing_if = "IndexMap::is_empty")]
+    pub generated: IndexMap<String, FileCounts>,
+    /// One row per counted file (`--by-file`), already sorted and truncated
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub by_file: Vec<PerFile>,
+    /// Directory prefix (`--by-dir --depth N`) -> totals and per-language breakdown
+    #[serde(skip_serializing_if = "IndexMap::is_empty")]
+    pub by_dir: IndexMap<String, GroupCounts>,
+    /// `CODEOWNERS` owner (`--by-owner`) -> totals and per-language breakdown. A file with
+    /// several owners counts towards each
+    #[serde(skip_serializing_if = "IndexMap::is_empty")]
+    pub by_owner: IndexMap<String, GroupCounts>,
     pub files_analyzed: usize,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub stats: Option<FileStats>,
@@ -39,12 +96,83 @@ pub struct AnalyzeResult {
     pub analyzed_path: Option<String>,
 }
 
+/// Lines under one group of files (a directory prefix or a code owner). Embedded lines are attributed to their own language
+/// in `languages` (as in `AnalyzeResult::per_lang`) and included in `totals`.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct GroupCounts {
+    pub totals: FileCounts,
+    pub languages: IndexMap<String, FileCounts>,
+}
+
+impl GroupCounts {
+    /// Add one file's analysis under `language`.
+    pub fn add(&mut self, language: &str, analysis: &FileAnalysis) {
+        self.totals.merge(&analysis.combined());
+        for (child, counts) in &analysis.embedded {
+            self.languages
+                .entry(child.clone())
+                .or_default()
+                .merge(counts);
+        }
+        self.languages
+            .entry(language.to_string())
+            .or_default()
+            .merge(&analysis.counts);
+    }
+
+    pub fn merge(&mut self, other: &GroupCounts) {
+        self.totals.merge(&other.totals);
+        for (lang, counts) in &other.languages {
+            self.languages
+                .entry(lang.clone())
+                .or_default()
+                .merge(counts);
+        }
+    }
+}
+
+/// Counts for a single file; `path` is relative to the scanned directory.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct PerFile {
+    pub path: String,
+    pub language: String,
+    pub code: usize,
+    pub comment: usize,
+    pub blank: usize,
+    pub doc: usize,
+    pub total: usize,
+    pub bytes: u64,
+    /// What decided the language (`extension`, `heuristic`, `shebang`, ...)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detected_by: Option<DetectionSource>,
+}
+
+/// Counts at one sampled commit of `ocloc history`.
+#[derive(Debug, Clone, Serialize)]
+pub struct HistoryPoint {
+    pub commit: String,
+    /// Commit time, RFC 3339 in UTC
+    pub date: String,
+    pub totals: FileCounts,
+    pub languages: IndexMap<String, FileCounts>,
+}
+
 #[derive(Debug, Clone, Default, Serialize)]
 pub struct FileStats {
     pub total_files: usize,
     pub unique_files: usize,
     pub ignored_files: usize,
     pub empty_files: usize,
+    /// Generated files: `linguist-generated`, marker comments, minified content and
+    /// lockfiles (left out of the totals unless `--include-generated`)
+    pub generated_files: usize,
+    /// Files marked `linguist-vendored` and not generated (skipped likewise)
+    pub vendored_files: usize,
+    /// Files with a known extension whose content turned out to be binary
+    pub binary_files: usize,
+    /// `--scan-cache`: files whose cached counts were reused, and files (re)analyzed
+    pub cache_hits: usize,
+    pub cache_misses: usize,
     pub elapsed_seconds: f64,
 }
 
@@ -63,6 +191,7 @@ mod tests {
                 code: 8,
                 comment: 1,
                 blank: 1,
+                doc: 0,
             },
         );
         per.insert(
@@ -73,6 +202,7 @@ mod tests {
                 code: 3,
                 comment: 2,
                 blank: 0,
+                doc: 0,
             },
         );
         let mut totals = FileCounts::default();
@@ -82,6 +212,11 @@ mod tests {
         let a = AnalyzeResult {
             per_lang: per,
             totals,
+            embedded: IndexMap::new(),
+            generated: IndexMap::new(),
+            by_file: Vec::new(),
+            by_dir: IndexMap::new(),
+            by_owner: IndexMap::new(),
             files_analyzed: totals.files,
             stats: None,
             analyzed_path: None,
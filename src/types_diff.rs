@@ -1,6 +1,9 @@
 use indexmap::IndexMap;
 use serde::Serialize;
 
+use crate::analyzer::LineKind;
+use crate::types::FileCounts;
+
 #[derive(Debug, Clone, Copy, Default, Serialize)]
 pub struct LineDelta {
     pub files: isize,
@@ -8,30 +11,90 @@ pub struct LineDelta {
     pub code_removed: isize,
     pub comment_added: isize,
     pub blank_added: isize,
+    pub doc_added: isize,
     pub total_net: isize,
+    /// Lines added and removed in the diff hunks, with `--hunks`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub churn: Option<LineChurn>,
+}
+
+/// Lines added and removed in a file's diff hunks, by their classification on the side
+/// they belong to (`--hunks`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct LineChurn {
+    pub code_added: usize,
+    pub code_removed: usize,
+    pub comment_added: usize,
+    pub comment_removed: usize,
+    pub blank_added: usize,
+    pub blank_removed: usize,
+    pub doc_added: usize,
+    pub doc_removed: usize,
+}
+
+impl LineChurn {
+    pub fn add_line(&mut self, kind: LineKind, added: bool) {
+        let (a, r) = match kind {
+            LineKind::Code => (&mut self.code_added, &mut self.code_removed),
+            LineKind::Comment => (&mut self.comment_added, &mut self.comment_removed),
+            LineKind::Blank => (&mut self.blank_added, &mut self.blank_removed),
+            LineKind::Doc => (&mut self.doc_added, &mut self.doc_removed),
+        };
+        *(if added { a } else { r }) += 1;
+    }
+
+    pub fn merge(&mut self, other: &LineChurn) {
+        self.code_added += other.code_added;
+        self.code_removed += other.code_removed;
+        self.comment_added += other.comment_added;
+        self.comment_removed += other.comment_removed;
+        self.blank_added += other.blank_added;
+        self.blank_removed += other.blank_removed;
+        self.doc_added += other.doc_added;
+        self.doc_removed += other.doc_removed;
+    }
 }
 
 impl LineDelta {
-    pub fn add_file_delta(&mut self, base: (usize, usize, usize), head: (usize, usize, usize)) {
-        let (base_code, base_comment, base_blank) = base;
-        let (head_code, head_comment, head_blank) = head;
+    pub fn add_file_delta(&mut self, base: &FileCounts, head: &FileCounts) {
         self.files += 1;
         // Track added vs removed for code
-        if head_code >= base_code {
-            self.code_added += (head_code - base_code) as isize;
+        if head.code >= base.code {
+            self.code_added += (head.code - base.code) as isize;
         } else {
-            self.code_removed += (base_code - head_code) as isize;
+            self.code_removed += (base.code - head.code) as isize;
+        }
+        // Only track additions for comment/blank/doc per plan
+        if head.comment > base.comment {
+            self.comment_added += (head.comment - base.comment) as isize;
         }
-        // Only track additions for comment/blank per plan
-        if head_comment > base_comment {
-            self.comment_added += (head_comment - base_comment) as isize;
+        if head.blank > base.blank {
+            self.blank_added += (head.blank - base.blank) as isize;
         }
-        if head_blank > base_blank {
-            self.blank_added += (head_blank - base_blank) as isize;
+        if head.doc > base.doc {
+            self.doc_added += (head.doc - base.doc) as isize;
         }
         // Net total change across all categories
-        self.total_net += (head_code + head_comment + head_blank) as isize
-            - (base_code + base_comment + base_blank) as isize;
+        self.total_net += head.total as isize - base.total as isize;
+    }
+
+    /// `add_file_delta`, plus the file's lines added and removed in the diff hunks.
+    pub fn add_file_churn(&mut self, base: &FileCounts, head: &FileCounts, churn: &LineChurn) {
+        self.add_file_delta(base, head);
+        self.churn.get_or_insert_default().merge(churn);
+    }
+
+    pub fn merge(&mut self, other: &LineDelta) {
+        self.files += other.files;
+        self.code_added += other.code_added;
+        self.code_removed += other.code_removed;
+        self.comment_added += other.comment_added;
+        self.blank_added += other.blank_added;
+        self.doc_added += other.doc_added;
+        self.total_net += other.total_net;
+        if let Some(churn) = &other.churn {
+            self.churn.get_or_insert_default().merge(churn);
+        }
     }
 }
 
@@ -50,7 +113,11 @@ pub struct DiffPerFile {
     pub code_delta: isize,
     pub comment_delta: isize,
     pub blank_delta: isize,
+    pub doc_delta: isize,
     pub total_delta: isize,
+    /// Lines added and removed in the diff hunks, with `--hunks`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub churn: Option<LineChurn>,
 }
 
 #[derive(Debug, Clone, Serialize, Default)]
@@ -70,4 +137,6 @@ pub struct DiffSummary {
     #[serde(skip_serializing_if = "Vec::is_empty")]
     pub by_file: Vec<DiffPerFile>,
     pub totals: LineDelta,
+    /// Languages, totals and files carry `churn` from the diff hunks (`--hunks`)
+    pub hunks: bool,
 }
@@ -1,5 +1,9 @@
 use anyhow::{Context, Result, anyhow};
-use git2::{Delta, DiffOptions, Oid, Repository};
+use git2::{
+    AttrCheckFlags, AttrValue, Delta, DiffOptions, ObjectType, Oid, Patch, Repository,
+    TreeWalkMode, TreeWalkResult,
+};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
 use std::path::{Path, PathBuf};
 
 //
@@ -133,6 +137,106 @@ impl VcsContext {
         Ok(out)
     }
 
+    /// Commits on the first-parent chain from `from` (default: the root commit) to `to`,
+    /// both included, oldest first, with their commit times in seconds since the epoch.
+    pub fn first_parent_commits(&self, from: Option<&str>, to: &str) -> Result<Vec<(Oid, i64)>> {
+        let peel = |rev: &str| -> Result<Oid> {
+            let commit = self
+                .repo
+                .revparse_single(rev)
+                .and_then(|obj| obj.peel_to_commit())
+                .with_context(|| format!("resolve commit {rev}"))?;
+            Ok(commit.id())
+        };
+        let stop = from.map(peel).transpose()?;
+        let mut walk = self.repo.revwalk()?;
+        walk.push(peel(to)?)?;
+        walk.simplify_first_parent()?;
+        let mut out = Vec::new();
+        for oid in walk {
+            let oid = oid?;
+            let time = self.repo.find_commit(oid)?.time().seconds();
+            out.push((oid, time));
+            if Some(oid) == stop {
+                break;
+            }
+        }
+        if let (Some(stop), Some(from)) = (stop, from) {
+            if out.last().map(|&(oid, _)| oid) != Some(stop) {
+                return Err(anyhow!("{from} is not a first-parent ancestor of {to}"));
+            }
+        }
+        out.reverse();
+        Ok(out)
+    }
+
+    /// Blobs in the tree of `rev` (a commit, tag or branch) under the repository-relative
+    /// directory `prefix` (empty for the whole tree), with paths relative to `prefix`.
+    /// Symlinks and submodules are skipped.
+    pub fn tree_files(&self, rev: &str, prefix: &Path) -> Result<Vec<(PathBuf, Oid)>> {
+        let tree = self
+            .repo
+            .revparse_single(rev)
+            .with_context(|| format!("resolve rev {rev}"))?
+            .peel_to_tree()
+            .with_context(|| format!("{rev} does not name a commit or tree"))?;
+        let tree = if prefix.as_os_str().is_empty() {
+            tree
+        } else {
+            let entry = tree
+                .get_path(prefix)
+                .with_context(|| format!("{} not found in {rev}", prefix.display()))?;
+            self.repo
+                .find_tree(entry.id())
+                .with_context(|| format!("{} is not a directory in {rev}", prefix.display()))?
+        };
+        let mut out = Vec::new();
+        tree.walk(TreeWalkMode::PreOrder, |dir, entry| {
+            let is_file = entry.kind() == Some(ObjectType::Blob) && entry.filemode() != 0o120000;
+            if let (true, Some(name)) = (is_file, entry.name()) {
+                out.push((Path::new(dir).join(name), entry.id()));
+            }
+            TreeWalkResult::Ok
+        })?;
+        Ok(out)
+    }
+
+    /// Linguist attributes from the `.gitattributes` files anywhere in the tree of `rev`.
+    pub fn tree_attributes(&self, rev: &str) -> Result<TreeAttributes> {
+        let tree = self
+            .repo
+            .revparse_single(rev)
+            .with_context(|| format!("resolve rev {rev}"))?
+            .peel_to_tree()
+            .with_context(|| format!("{rev} does not name a commit or tree"))?;
+        let mut files = Vec::new();
+        tree.walk(TreeWalkMode::PreOrder, |dir, entry| {
+            if entry.name() == Some(".gitattributes") && entry.kind() == Some(ObjectType::Blob) {
+                if let Some(text) = self.read_blob_bytes(Some(entry.id())) {
+                    files.push((
+                        PathBuf::from(dir),
+                        String::from_utf8_lossy(&text).into_owned(),
+                    ));
+                }
+            }
+            TreeWalkResult::Ok
+        })?;
+        // Shallower files first, so that deeper ones take precedence
+        files.sort_by_key(|(dir, _)| dir.components().count());
+        Ok(TreeAttributes::parse(
+            files
+                .iter()
+                .map(|(dir, text)| (dir.as_path(), text.as_str())),
+        ))
+    }
+
+    /// Content of the file at `path` (relative to the tree root) in revision `rev`.
+    pub fn read_tree_file(&self, rev: &str, path: &Path) -> Option<Vec<u8>> {
+        let tree = self.repo.revparse_single(rev).ok()?.peel_to_tree().ok()?;
+        let entry = tree.get_path(path).ok()?;
+        self.read_blob_bytes(Some(entry.id()))
+    }
+
     pub fn read_blob_bytes(&self, oid: Option<Oid>) -> Option<Vec<u8>> {
         let oid = oid?;
         let blob = self.repo.find_blob(oid).ok()?;
@@ -148,3 +252,193 @@ impl VcsContext {
         Some(blob.content().to_vec())
     }
 }
+
+/// Lines a diff of `old` to `new` touches: removed lines (1-based, numbered in `old`) and
+/// added lines (numbered in `new`). Binary content has no hunks.
+pub fn changed_lines(old: &[u8], new: &[u8]) -> Result<(Vec<usize>, Vec<usize>)> {
+    let mut opts = DiffOptions::new();
+    opts.context_lines(0);
+    let patch = Patch::from_buffers(old, None, new, None, Some(&mut opts)).context("diff blobs")?;
+    let mut removed = Vec::new();
+    let mut added = Vec::new();
+    for hunk in 0..patch.num_hunks() {
+        for n in 0..patch.num_lines_in_hunk(hunk)? {
+            let line = patch.line_in_hunk(hunk, n)?;
+            match line.origin() {
+                '-' => removed.extend(line.old_lineno().map(|l| l as usize)),
+                '+' => added.extend(line.new_lineno().map(|l| l as usize)),
+                _ => {}
+            }
+        }
+    }
+    Ok((removed, added))
+}
+
+/// The git work tree containing `path`, and `path` relative to it; `None` outside a work
+/// tree. Repository-wide files (`.gitattributes`, `CODEOWNERS`) are found and matched
+/// from the work tree root even when a subdirectory is scanned.
+pub fn work_tree_of(path: &Path) -> Option<(Repository, PathBuf)> {
+    let repo = Repository::discover(path).ok()?;
+    let workdir = repo.workdir()?.canonicalize().ok()?;
+    let prefix = path
+        .canonicalize()
+        .ok()?
+        .strip_prefix(&workdir)
+        .ok()?
+        .to_path_buf();
+    Some((repo, prefix))
+}
+
+/// GitHub linguist attributes of a file, from `.gitattributes`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LinguistAttrs {
+    /// `linguist-language=X`
+    pub language: Option<String>,
+    /// `linguist-generated`
+    pub generated: bool,
+    /// `linguist-vendored`
+    pub vendored: bool,
+}
+
+/// Looks up linguist attributes for files found by walking `root` inside a git work tree.
+/// Owns its `Repository`, so parallel walkers open one per thread.
+pub struct LinguistAttributes {
+    repo: Repository,
+    root: PathBuf,
+    // `root` relative to the work tree
+    prefix: PathBuf,
+}
+
+impl LinguistAttributes {
+    /// `None` when `root` is not inside a git work tree.
+    pub fn open(root: &Path) -> Option<Self> {
+        let (repo, prefix) = work_tree_of(root)?;
+        Some(Self {
+            repo,
+            root: root.to_path_buf(),
+            prefix,
+        })
+    }
+
+    pub fn lookup(&self, path: &Path) -> LinguistAttrs {
+        let Ok(rel) = path.strip_prefix(&self.root) else {
+            return LinguistAttrs::default();
+        };
+        let rel = self.prefix.join(rel);
+        LinguistAttrs::from_values(|name| {
+            let value = self
+                .repo
+                .get_attr(&rel, name, AttrCheckFlags::FILE_THEN_INDEX)
+                .ok()
+                .flatten();
+            AttrValue::from_string(value)
+        })
+    }
+}
+
+impl LinguistAttrs {
+    fn from_values<'a>(get: impl Fn(&str) -> AttrValue<'a>) -> Self {
+        // Set (`linguist-generated`) or `=true`; unset and `=false` leave the file counted
+        let is_set = |name| {
+            matches!(
+                get(name),
+                AttrValue::True | AttrValue::String("true") | AttrValue::String("1")
+            )
+        };
+        LinguistAttrs {
+            language: match get("linguist-language") {
+                AttrValue::String(lang) => Some(lang.to_string()),
+                _ => None,
+            },
+            generated: is_set("linguist-generated"),
+            vendored: is_set("linguist-vendored"),
+        }
+    }
+}
+
+// State of an attribute on a `.gitattributes` line
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum AttrState {
+    Set,
+    Unset,
+    Unspecified,
+    Value(String),
+}
+
+struct AttrRule {
+    // Directory of the `.gitattributes` file, relative to the tree root
+    dir: PathBuf,
+    matcher: Gitignore,
+    attrs: Vec<(String, AttrState)>,
+}
+
+/// Linguist attributes of the files in a revision, from the `.gitattributes` files of its
+/// tree; the work tree and the index are not consulted. As in git, a file's patterns apply
+/// below its directory, and deeper files and later lines take precedence.
+pub struct TreeAttributes {
+    rules: Vec<AttrRule>,
+}
+
+impl TreeAttributes {
+    /// Parse `.gitattributes` files given as (directory, content), shallowest first.
+    /// Macro definitions and lines with an invalid pattern are skipped.
+    pub fn parse<'a>(files: impl IntoIterator<Item = (&'a Path, &'a str)>) -> Self {
+        let mut rules = Vec::new();
+        for (dir, text) in files {
+            for line in text.lines() {
+                let line = line.trim();
+                if line.is_empty() || line.starts_with('#') || line.starts_with("[attr]") {
+                    continue;
+                }
+                let mut fields = line.split_whitespace();
+                let Some(pattern) = fields.next() else {
+                    continue;
+                };
+                let attrs = fields
+                    .map(|field| match field.split_once('=') {
+                        Some((name, value)) => (name.to_string(), AttrState::Value(value.into())),
+                        None => match field.as_bytes()[0] {
+                            b'-' => (field[1..].to_string(), AttrState::Unset),
+                            b'!' => (field[1..].to_string(), AttrState::Unspecified),
+                            _ => (field.to_string(), AttrState::Set),
+                        },
+                    })
+                    .collect();
+                // Negative patterns are not allowed in `.gitattributes`
+                let mut builder = GitignoreBuilder::new("");
+                if pattern.starts_with('!') || builder.add_line(None, pattern).is_err() {
+                    continue;
+                }
+                if let Ok(matcher) = builder.build() {
+                    rules.push(AttrRule {
+                        dir: dir.to_path_buf(),
+                        matcher,
+                        attrs,
+                    });
+                }
+            }
+        }
+        Self { rules }
+    }
+
+    /// Attributes of the file at `rel`, relative to the tree root.
+    pub fn lookup(&self, rel: &Path) -> LinguistAttrs {
+        LinguistAttrs::from_values(|name| {
+            let state = self
+                .rules
+                .iter()
+                .rev()
+                .filter(|rule| {
+                    rel.strip_prefix(&rule.dir)
+                        .is_ok_and(|rel| rule.matcher.matched(rel, false).is_ignore())
+                })
+                .find_map(|rule| rule.attrs.iter().rev().find(|(n, _)| n == name));
+            match state {
+                Some((_, AttrState::Set)) => AttrValue::True,
+                Some((_, AttrState::Unset)) => AttrValue::False,
+                Some((_, AttrState::Value(value))) => AttrValue::String(value),
+                Some((_, AttrState::Unspecified)) | None => AttrValue::Unspecified,
+            }
+        })
+    }
+}
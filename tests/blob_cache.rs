@@ -0,0 +1,50 @@
+use std::fs;
+
+mod common;
+
+use common::{git, ocloc};
+
+fn languages(stdout: &[u8]) -> serde_json::Value {
+    let v: serde_json::Value = serde_json::from_slice(stdout).expect("valid json");
+    v["languages"].clone()
+}
+
+#[test]
+fn rev_and_diff_share_the_blob_cache() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    git(root, &["init", "-q"]);
+    fs::write(root.join("main.rs"), "// entry\nfn main() {}\n").unwrap();
+    fs::write(root.join("tool.py"), "print(1)\n").unwrap();
+    git(root, &["add", "."]);
+    git(root, &["commit", "-q", "-m", "one"]);
+    fs::write(
+        root.join("main.rs"),
+        "// entry\nfn main() {}\nfn helper() {}\n",
+    )
+    .unwrap();
+    git(root, &["commit", "-q", "-am", "two"]);
+    let cache_dir = root.join(".git/ocloc-cache");
+
+    let uncached = ocloc(root, &[".", "--rev", "HEAD", "--json", "--no-cache"]);
+    assert!(!cache_dir.exists());
+
+    let first = ocloc(root, &[".", "--rev", "HEAD", "--json"]);
+    assert_eq!(fs::read_dir(&cache_dir).unwrap().count(), 1);
+    let second = ocloc(root, &[".", "--rev", "HEAD", "--json"]);
+    assert_eq!(languages(&first), languages(&uncached));
+    assert_eq!(languages(&second), languages(&uncached));
+    assert_eq!(languages(&second)["Rust"]["code"], 2);
+
+    let diff: serde_json::Value =
+        serde_json::from_slice(&ocloc(root, &["diff", "--json"])).unwrap();
+    assert_eq!(diff["totals"]["code_added"], 1);
+    let diff: serde_json::Value =
+        serde_json::from_slice(&ocloc(root, &["diff", "--json", "--no-cache"])).unwrap();
+    assert_eq!(diff["totals"]["code_added"], 1);
+
+    let out = ocloc(root, &["cache", "prune"]);
+    assert!(String::from_utf8_lossy(&out).contains("0 cache file(s)"));
+    ocloc(root, &["cache", "prune", "--all"]);
+    assert!(!cache_dir.exists());
+}
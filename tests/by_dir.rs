@@ -0,0 +1,44 @@
+use std::fs;
+
+#[test]
+fn by_dir_groups_totals_by_directory_prefix() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    fs::create_dir_all(root.join("services/auth/src")).unwrap();
+    fs::create_dir_all(root.join("services/billing")).unwrap();
+    fs::create_dir_all(root.join("libs/core")).unwrap();
+    fs::write(
+        root.join("services/auth/src/main.rs"),
+        "fn main() {}\n// c\n",
+    )
+    .unwrap();
+    fs::write(root.join("services/auth/run.py"), "print(1)\n").unwrap();
+    fs::write(
+        root.join("services/billing/lib.rs"),
+        "fn a() {}\nfn b() {}\n",
+    )
+    .unwrap();
+    fs::write(root.join("libs/core/lib.rs"), "fn c() {}\n").unwrap();
+    fs::write(root.join("build.rs"), "fn main() {}\n").unwrap();
+
+    let out = std::process::Command::new(env!("CARGO_BIN_EXE_ocloc"))
+        .arg(root)
+        .args(["--json", "--by-dir", "--depth", "2"])
+        .output()
+        .expect("run ocloc");
+    assert!(out.status.success());
+    let v: serde_json::Value = serde_json::from_slice(&out.stdout).expect("valid json");
+    let dirs = v["by_dir"].as_object().unwrap();
+    let keys: Vec<&str> = dirs.keys().map(String::as_str).collect();
+    assert_eq!(
+        keys,
+        [".", "libs/core", "services/auth", "services/billing"]
+    );
+    let auth = &dirs["services/auth"];
+    assert_eq!(auth["totals"]["files"], 2);
+    assert_eq!(auth["totals"]["code"], 2);
+    assert_eq!(auth["languages"]["Rust"]["comment"], 1);
+    assert_eq!(auth["languages"]["Python"]["code"], 1);
+    assert_eq!(dirs["services/billing"]["totals"]["code"], 2);
+    assert_eq!(dirs["."]["languages"]["Rust"]["files"], 1);
+}
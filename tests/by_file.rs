@@ -0,0 +1,118 @@
+use std::fs;
+use std::path::Path;
+
+fn run(root: &Path, extra: &[&str]) -> String {
+    let out = std::process::Command::new(env!("CARGO_BIN_EXE_ocloc"))
+        .arg(root)
+        .args(extra)
+        .output()
+        .expect("run ocloc");
+    assert!(out.status.success());
+    String::from_utf8(out.stdout).unwrap()
+}
+
+fn fixture() -> tempfile::TempDir {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    fs::create_dir_all(root.join("src")).unwrap();
+    fs::write(root.join("src/big.rs"), "fn a() {}\n".repeat(10)).unwrap();
+    fs::write(root.join("src/small.rs"), "// one\nfn b() {}\n").unwrap();
+    fs::write(
+        root.join("tool.py"),
+        format!("# {}\nx = 1\n", "-".repeat(200)),
+    )
+    .unwrap();
+    dir
+}
+
+#[test]
+fn by_file_lists_sorted_files_in_json() {
+    let dir = fixture();
+    let v: serde_json::Value =
+        serde_json::from_str(&run(dir.path(), &["--by-file", "--json"])).unwrap();
+    let files = v["by_file"].as_array().unwrap();
+    let paths: Vec<&str> = files.iter().map(|f| f["path"].as_str().unwrap()).collect();
+    let sep = std::path::MAIN_SEPARATOR;
+    assert_eq!(
+        paths,
+        [
+            format!("src{sep}big.rs"),
+            format!("src{sep}small.rs"),
+            "tool.py".to_string()
+        ]
+    );
+    assert_eq!(files[0]["language"], "Rust");
+    assert_eq!(files[0]["code"], 10);
+    assert_eq!(files[0]["bytes"], 100);
+
+    let v: serde_json::Value = serde_json::from_str(&run(
+        dir.path(),
+        &["--by-file", "--json", "--sort", "bytes", "--top", "1"],
+    ))
+    .unwrap();
+    let files = v["by_file"].as_array().unwrap();
+    assert_eq!(files.len(), 1);
+    assert_eq!(files[0]["path"], "tool.py");
+    // Language totals are unaffected by --top
+    assert_eq!(v["totals"]["files"], 3);
+}
+
+#[test]
+fn by_file_appears_in_csv_and_table() {
+    let dir = fixture();
+    let csv = run(dir.path(), &["--by-file", "--csv", "--sort", "comment"]);
+    let section = csv.split("\n\n").nth(1).unwrap();
+    let mut lines = section.lines();
+    assert_eq!(
+        lines.next(),
+        Some("path,language,code,comment,blank,total,doc,bytes,detected_by")
+    );
+    assert!(
+        lines
+            .next()
+            .unwrap()
+            .ends_with("small.rs,Rust,1,1,0,2,0,17,extension")
+    );
+
+    let table = run(dir.path(), &["--by-file"]);
+    assert!(table.contains("File"));
+    assert!(table.contains("tool.py"));
+    // Without the flag there is no listing
+    assert!(!run(dir.path(), &["--json"]).contains("by_file"));
+}
+
+#[test]
+fn by_file_reports_how_each_language_was_detected() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    fs::write(root.join("main.rs"), "fn main() {}\n").unwrap();
+    fs::write(root.join("api.h"), "#pragma once\nnamespace app {}\n").unwrap();
+    fs::write(root.join("plain.h"), "int add(int a, int b);\n").unwrap();
+    fs::write(root.join("deploy"), "#!/usr/bin/env python3\nprint(1)\n").unwrap();
+    fs::write(root.join("Dockerfile"), "FROM alpine\n").unwrap();
+
+    let v: serde_json::Value =
+        serde_json::from_str(&run(root, &["--by-file", "--json", "--sort", "path"])).unwrap();
+    let detected: Vec<(&str, &str, &str)> = v["by_file"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|f| {
+            (
+                f["path"].as_str().unwrap(),
+                f["language"].as_str().unwrap(),
+                f["detected_by"].as_str().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        detected,
+        [
+            ("Dockerfile", "Dockerfile", "filename"),
+            ("api.h", "C++", "heuristic"),
+            ("deploy", "Python", "shebang"),
+            ("main.rs", "Rust", "extension"),
+            ("plain.h", "C", "extension"),
+        ]
+    );
+}
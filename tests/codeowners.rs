@@ -0,0 +1,92 @@
+use std::fs;
+use std::path::Path;
+
+mod common;
+
+use common::{git, ocloc_json};
+
+fn run(root: &Path, extra: &[&str]) -> std::process::Output {
+    std::process::Command::new(env!("CARGO_BIN_EXE_ocloc"))
+        .arg(root)
+        .args(extra)
+        .output()
+        .expect("run ocloc")
+}
+
+#[test]
+fn by_owner_builds_a_team_by_language_matrix() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    fs::create_dir_all(root.join(".github")).unwrap();
+    fs::create_dir_all(root.join("services/auth")).unwrap();
+    fs::create_dir_all(root.join("web")).unwrap();
+    fs::write(
+        root.join(".github/CODEOWNERS"),
+        "* @org/platform\n/services/auth/ @org/auth @org/security\n*.js @org/web\n",
+    )
+    .unwrap();
+    fs::write(root.join("main.rs"), "fn main() {}\n").unwrap();
+    fs::write(root.join("services/auth/lib.rs"), "fn a() {}\nfn b() {}\n").unwrap();
+    fs::write(root.join("services/auth/login.js"), "let x = 1;\n").unwrap();
+    fs::write(root.join("web/app.js"), "let y = 2;\n// c\n").unwrap();
+
+    let out = run(root, &["--json", "--by-owner"]);
+    assert!(out.status.success());
+    let v: serde_json::Value = serde_json::from_slice(&out.stdout).expect("valid json");
+    let owners = &v["by_owner"];
+    assert_eq!(owners["@org/platform"]["languages"]["Rust"]["files"], 1);
+    // Both auth owners get the Rust file; the later `*.js` rule takes login.js
+    for team in ["@org/auth", "@org/security"] {
+        assert_eq!(owners[team]["languages"]["Rust"]["code"], 2);
+        assert!(owners[team]["languages"].get("JavaScript").is_none());
+    }
+    assert_eq!(owners["@org/web"]["languages"]["JavaScript"]["files"], 2);
+    assert_eq!(owners["@org/web"]["totals"]["comment"], 1);
+
+    let csv = String::from_utf8(run(root, &["--csv", "--by-owner"]).stdout).unwrap();
+    assert!(csv.contains("\nowner,language,files,code,comment,blank,total,doc\n"));
+    assert!(csv.contains("\n@org/web,JavaScript,2,2,1,0,3,0\n"));
+}
+
+#[test]
+fn by_owner_requires_a_codeowners_file() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("main.rs"), "fn main() {}\n").unwrap();
+    let out = run(dir.path(), &["--by-owner"]);
+    assert!(!out.status.success());
+    assert!(String::from_utf8_lossy(&out.stderr).contains("CODEOWNERS"));
+}
+
+#[test]
+fn by_owner_in_a_subdirectory_uses_the_repository_codeowners() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    fs::create_dir_all(root.join(".github")).unwrap();
+    fs::create_dir_all(root.join("services/auth")).unwrap();
+    fs::create_dir_all(root.join("services/billing")).unwrap();
+    fs::write(
+        root.join(".github/CODEOWNERS"),
+        "* @org/platform\n/services/auth/ @org/auth\n",
+    )
+    .unwrap();
+    fs::write(root.join("services/auth/lib.rs"), "fn a() {}\n").unwrap();
+    fs::write(
+        root.join("services/billing/lib.rs"),
+        "fn b() {}\nfn c() {}\n",
+    )
+    .unwrap();
+    git(root, &["init", "-q"]);
+    git(root, &["add", "."]);
+    git(root, &["commit", "-q", "-m", "one"]);
+
+    // Worktree scan and `--rev` of PATH = services/ match paths from the repository root
+    for extra in [&[][..], &["--rev", "HEAD"]] {
+        let v = ocloc_json(
+            root,
+            &[&["services", "--json", "--by-owner"], extra].concat(),
+        );
+        let owners = &v["by_owner"];
+        assert_eq!(owners["@org/auth"]["totals"]["code"], 1, "{extra:?}");
+        assert_eq!(owners["@org/platform"]["totals"]["code"], 2, "{extra:?}");
+    }
+}
@@ -0,0 +1,38 @@
+//! Helpers shared by integration tests that build git repositories and run `ocloc`.
+#![allow(dead_code)]
+
+use std::path::Path;
+use std::process::Command;
+
+/// Run `git` in `root` with a fixed identity; panics unless it succeeds.
+pub fn git(root: &Path, args: &[&str]) {
+    assert!(
+        Command::new("git")
+            .args(["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .current_dir(root)
+            .status()
+            .expect("run git")
+            .success()
+    );
+}
+
+/// Run `ocloc` in `dir` and return its stdout; panics with its stderr unless it succeeds.
+pub fn ocloc(dir: &Path, args: &[&str]) -> Vec<u8> {
+    let out = Command::new(env!("CARGO_BIN_EXE_ocloc"))
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .expect("run ocloc");
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    out.stdout
+}
+
+/// `ocloc` with JSON output, parsed.
+pub fn ocloc_json(dir: &Path, args: &[&str]) -> serde_json::Value {
+    serde_json::from_slice(&ocloc(dir, args)).expect("valid json")
+}
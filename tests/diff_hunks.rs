@@ -0,0 +1,125 @@
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+mod common;
+
+use common::{git, ocloc_json};
+
+fn diff_json(root: &Path, extra: &[&str]) -> serde_json::Value {
+    ocloc_json(root, &[&["diff", "--json", "--no-cache"], extra].concat())
+}
+
+#[test]
+fn hunks_report_rewritten_lines() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    git(root, &["init", "-q"]);
+    fs::write(
+        root.join("lib.rs"),
+        "/*\n old note\n*/\nfn a() { 1 }\nfn b() { 2 }\nfn c() { 3 }\n",
+    )
+    .unwrap();
+    git(root, &["add", "."]);
+    git(root, &["commit", "-q", "-m", "one"]);
+    // Three functions rewritten, a line inside the block comment reworded, a blank added
+    fs::write(
+        root.join("lib.rs"),
+        "/*\n new note\n*/\n\nfn a() { 10 }\nfn b() { 20 }\nfn c() { 30 }\n",
+    )
+    .unwrap();
+    git(root, &["commit", "-q", "-am", "two"]);
+
+    let net = diff_json(root, &[]);
+    assert_eq!(net["hunks"], false);
+    assert_eq!(net["totals"]["code_added"], 0);
+    assert_eq!(net["totals"]["code_removed"], 0);
+
+    let hunks = diff_json(root, &["--hunks", "--by-file"]);
+    assert_eq!(hunks["hunks"], true);
+    let rust = &hunks["languages"]["Rust"];
+    assert_eq!(rust["files"], 1);
+    // The net fields keep their meaning; hunk counts are under `churn`
+    assert_eq!(rust["code_added"], 0);
+    assert_eq!(rust["blank_added"], 1);
+    assert!(rust.get("comment_removed").is_none());
+    assert_eq!(rust["total_net"], 1);
+    let churn = &rust["churn"];
+    assert_eq!(churn["code_added"], 3);
+    assert_eq!(churn["code_removed"], 3);
+    assert_eq!(churn["comment_added"], 1);
+    assert_eq!(churn["comment_removed"], 1);
+    assert_eq!(churn["blank_added"], 1);
+    assert_eq!(churn["blank_removed"], 0);
+    assert_eq!(hunks["totals"]["churn"]["code_added"], 3);
+    assert_eq!(hunks["by_file"][0]["churn"], *churn);
+    assert!(net["totals"].get("churn").is_none());
+}
+
+#[test]
+fn hunks_diff_decoded_text() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    git(root, &["init", "-q"]);
+    let utf16 = |text: &str| -> Vec<u8> {
+        let mut bytes = vec![0xFF, 0xFE];
+        bytes.extend(text.encode_utf16().flat_map(u16::to_le_bytes));
+        bytes
+    };
+    fs::write(root.join("a.py"), utf16("# note\nx = 1\ny = 2\n")).unwrap();
+    git(root, &["add", "."]);
+    git(root, &["commit", "-q", "-m", "one"]);
+    fs::write(root.join("a.py"), utf16("# note\nx = 10\ny = 2\n")).unwrap();
+    git(root, &["commit", "-q", "-am", "two"]);
+
+    let churn = &diff_json(root, &["--hunks"])["languages"]["Python"]["churn"];
+    assert_eq!(churn["code_added"], 1);
+    assert_eq!(churn["code_removed"], 1);
+    assert_eq!(churn["comment_added"], 0);
+}
+
+#[test]
+fn hunks_gate_thresholds_on_lines_added() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    git(root, &["init", "-q"]);
+    fs::write(
+        root.join("lib.rs"),
+        "fn a() { 1 }\nfn b() { 2 }\nfn c() { 3 }\n",
+    )
+    .unwrap();
+    git(root, &["add", "."]);
+    git(root, &["commit", "-q", "-m", "one"]);
+    fs::write(
+        root.join("lib.rs"),
+        "fn a() { 10 }\nfn b() { 20 }\nfn c() { 30 }\n",
+    )
+    .unwrap();
+    git(root, &["commit", "-q", "-am", "two"]);
+
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_ocloc"))
+            .args(["diff", "--json", "--no-cache", "--fail-on-threshold"])
+            .args(args)
+            .current_dir(root)
+            .output()
+            .expect("run ocloc")
+    };
+    // The net code delta is zero, but three code lines were rewritten
+    for (flag, under, at, message) in [
+        (
+            "--max-code-added",
+            "2",
+            "3",
+            "code added 3 exceeds threshold 2",
+        ),
+        ("--max-code-added-lang", "Rust:2", "Rust:3", "Rust>2"),
+    ] {
+        assert!(run(&[flag, under]).status.success());
+        let out = run(&[flag, under, "--hunks"]);
+        assert!(!out.status.success());
+        let stderr = String::from_utf8_lossy(&out.stderr);
+        assert!(stderr.contains(message), "{stderr}");
+        assert!(run(&[flag, at, "--hunks"]).status.success());
+    }
+}
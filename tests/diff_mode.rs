@@ -213,3 +213,55 @@ fn diff_mode_reports_deletions_and_removed_code() {
     // code_removed should be >= 1
     assert!(v["totals"]["code_removed"].as_i64().unwrap() >= 1);
 }
+
+#[test]
+fn diff_of_many_files_is_ordered_by_path() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    let git = |args: &[&str]| {
+        assert!(
+            std::process::Command::new("git")
+                .args(["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+                .args(args)
+                .current_dir(root)
+                .status()
+                .unwrap()
+                .success()
+        );
+    };
+    git(&["init", "-q"]);
+    for i in 0..60 {
+        fs::write(root.join(format!("f{i:02}.rs")), "fn a() {}\n").unwrap();
+    }
+    git(&["add", "."]);
+    git(&["commit", "-q", "-m", "initial"]);
+    for i in 0..60 {
+        let body = "fn a() {}\n".repeat(i % 5 + 2);
+        fs::write(root.join(format!("f{i:02}.rs")), body).unwrap();
+    }
+    git(&["commit", "-q", "-am", "grow"]);
+
+    let run = || {
+        let out = std::process::Command::new(env!("CARGO_BIN_EXE_ocloc"))
+            .args(["diff", "--json", "--by-file", "--no-cache"])
+            .current_dir(root)
+            .output()
+            .unwrap();
+        assert!(out.status.success());
+        serde_json::from_slice::<serde_json::Value>(&out.stdout).unwrap()
+    };
+    let v = run();
+    let paths: Vec<&str> = v["by_file"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|f| f["path"].as_str().unwrap())
+        .collect();
+    let mut sorted = paths.clone();
+    sorted.sort();
+    assert_eq!(paths.len(), 60);
+    assert_eq!(paths, sorted);
+    // 60 files growing by 1..=5 lines: 12 * (1 + 2 + 3 + 4 + 5)
+    assert_eq!(v["totals"]["code_added"], 180);
+    assert_eq!(run(), v);
+}
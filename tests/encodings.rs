@@ -0,0 +1,27 @@
+use std::fs;
+
+#[test]
+fn utf16_files_are_counted_and_binaries_skipped() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    let mut utf16 = vec![0xFF, 0xFE];
+    for unit in "// Saved by Visual Studio as UTF-16\r\nclass A { }\r\n".encode_utf16() {
+        utf16.extend_from_slice(&unit.to_le_bytes());
+    }
+    fs::write(root.join("A.cs"), utf16).unwrap();
+    fs::write(root.join("main.rs"), "fn main() {}\n").unwrap();
+    fs::write(root.join("blob.rs"), b"\x00\x01\x02\x03fn main() {}\n").unwrap();
+
+    let out = std::process::Command::new(env!("CARGO_BIN_EXE_ocloc"))
+        .arg(root)
+        .arg("--json")
+        .output()
+        .expect("run ocloc");
+    assert!(out.status.success());
+    let v: serde_json::Value = serde_json::from_slice(&out.stdout).expect("valid json");
+    let langs = &v["languages"];
+    assert_eq!(langs["C#"]["code"], 1);
+    assert_eq!(langs["C#"]["comment"], 1);
+    assert_eq!(langs["Rust"]["files"], 1);
+    assert_eq!(v["stats"]["binary_files"], 1);
+}
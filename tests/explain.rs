@@ -0,0 +1,52 @@
+use std::fs;
+use std::path::Path;
+
+fn explain(path: &Path) -> String {
+    let out = std::process::Command::new(env!("CARGO_BIN_EXE_ocloc"))
+        .arg("--explain")
+        .arg(path)
+        .output()
+        .expect("run ocloc");
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    String::from_utf8(out.stdout).expect("utf-8 output")
+}
+
+#[test]
+fn explain_prints_the_decoded_lines() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let utf16 = dir.path().join("utf16.cs");
+    let mut bytes = vec![0xFF, 0xFE];
+    for unit in "// note\r\nint x;\r\n".encode_utf16() {
+        bytes.extend(unit.to_le_bytes());
+    }
+    fs::write(&utf16, bytes).unwrap();
+    let out = explain(&utf16);
+    assert!(out.contains("1  comment  // note\n"), "{out}");
+    assert!(out.contains("2  code     int x;\n"), "{out}");
+
+    // Shebangs and heuristics see the decoded text too
+    let script = dir.path().join("script");
+    let mut bytes = vec![0xFF, 0xFE];
+    for unit in "#!/usr/bin/env python3\nprint(1)\n".encode_utf16() {
+        bytes.extend(unit.to_le_bytes());
+    }
+    fs::write(&script, bytes).unwrap();
+    let out = explain(&script);
+    assert!(out.contains(": Python (by shebang)"), "{out}");
+
+    let bom = dir.path().join("bom.rs");
+    fs::write(&bom, b"\xEF\xBB\xBF// note\nfn main() {}\n").unwrap();
+    let out = explain(&bom);
+    assert!(out.contains("1  comment  // note\n"), "{out}");
+    assert!(!out.contains('\u{feff}'));
+
+    let binary = dir.path().join("blob.rs");
+    fs::write(&binary, b"fn\0main\n").unwrap();
+    let out = explain(&binary);
+    assert!(out.contains("binary content"), "{out}");
+}
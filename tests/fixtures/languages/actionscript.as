@@ -0,0 +1,11 @@
+package {
+    import flash.display.Sprite;
+
+    /** The application class. */
+    public class Main extends Sprite {
+        public function Main() {
+            // print a greeting
+            trace("Hello /* not a comment */");
+        }
+    }
+}
@@ -0,0 +1,12 @@
+grammar Expr;
+
+/** An arithmetic expression. */
+expr: expr ('*' | '/') expr
+    | expr ('+' | '-') expr
+    | INT
+    ;
+
+// tokens
+INT: [0-9]+;
+COMMENT: '/*' .*? '*/' -> skip;
+WS: [ \t\r\n]+ -> skip;
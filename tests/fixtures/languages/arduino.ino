@@ -0,0 +1,14 @@
+// Blink the built-in LED.
+const int LED = LED_BUILTIN;
+
+void setup() {
+  pinMode(LED, OUTPUT);
+  Serial.begin(9600);
+}
+
+void loop() {
+  /* toggle every second */
+  digitalWrite(LED, !digitalRead(LED));
+  Serial.println("tick // not a comment");
+  delay(1000);
+}
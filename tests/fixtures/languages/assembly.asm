@@ -0,0 +1,13 @@
+; Linux x86-64 hello world (NASM)
+section .data
+msg db "Hello ; not a comment", 10
+len equ $ - msg
+
+section .text
+global _start
+_start:
+    mov rax, 1      ; write
+    mov rdi, 1
+    mov rsi, msg
+    mov rdx, len
+    syscall
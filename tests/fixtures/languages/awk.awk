@@ -0,0 +1,6 @@
+# Sums the second column.
+BEGIN { FS = "," }
+{ total += $2 }   # trailing
+END {
+    printf "total # %d\n", total
+}
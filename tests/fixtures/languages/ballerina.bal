@@ -0,0 +1,12 @@
+import ballerina/io;
+
+# Prints a greeting.
+# + name - who to greet
+public function greet(string name) {
+    // string templates interpolate
+    io:println(string `Hello, ${name} // not a comment`);
+}
+
+public function main() {
+    greet("world");
+}
@@ -0,0 +1,8 @@
+@echo off
+REM Greets the user.
+setlocal
+set NAME=world
+
+:: labels with two colons are comments
+echo Hello, %NAME% REM not a comment
+endlocal
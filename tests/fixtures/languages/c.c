@@ -0,0 +1,12 @@
+#include <stdio.h>
+
+/** Prints the comment delimiters of C. */
+int main(void) {
+    // A char literal may hold a quote:
+    char quote = '"';
+    printf("/* %c */\n", quote); // prints a fake comment
+    /*
+     * A multi-line block comment.
+     */
+    return 0;
+}
@@ -0,0 +1,11 @@
+// Returns the n-th Fibonacci number.
+fn fib(n: u32) -> u32 {
+    if n < 2 {
+        return n;
+    }
+    fib(n - 1) + fib(n - 2) // recursive
+}
+
+fn main() {
+    println!("fib(10) = {} // not a comment", fib(10));
+}
@@ -0,0 +1,8 @@
+/* A greeting program. */
+config const name = "world";
+
+// Print the greeting.
+proc main() {
+  writeln("Hello, ", name, " // not a comment");
+  writeln('/* neither */'); // trailing
+}
@@ -0,0 +1,6 @@
+// A sine tone for one second.
+SinOsc s => dac;
+440 => s.freq;
+/* let time pass */
+1::second => now;
+<<< "done // not a comment" >>>;
@@ -0,0 +1,12 @@
+pragma circom 2.1.6;
+
+// Multiplies two signals.
+template Multiplier() {
+    signal input a;
+    signal input b;
+    signal output c;
+    /* one constraint */
+    c <== a * b;
+}
+
+component main = Multiplier();
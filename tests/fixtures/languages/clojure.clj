@@ -0,0 +1,7 @@
+(ns demo.core)
+
+;; Greets the user.
+(defn greet [name]
+  (str "Hello, " name " ; not a comment"))
+
+(println (greet "world")) ; trailing
@@ -0,0 +1,8 @@
+# Minimal project
+cmake_minimum_required(VERSION 3.20)
+project(demo C)
+
+#[[ Bracket comments
+    span several lines ]]
+set(GREETING "# not a comment")
+add_executable(demo main.c) # trailing
@@ -0,0 +1,8 @@
+;;; Greeting utilities.
+(defun greet (name)
+  (format nil "Hello, ~a ; not a comment" name))
+
+#| outer
+   #| nested |#
+   still a comment |#
+(print (greet "world"))
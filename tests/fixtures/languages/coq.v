@@ -0,0 +1,8 @@
+Require Import Arith.
+
+(* Addition is commutative. *)
+Theorem add_comm_demo : forall n m : nat, n + m = m + n.
+Proof.
+  (* outer (* nested *) comment *)
+  intros n m. apply Nat.add_comm.
+Qed.
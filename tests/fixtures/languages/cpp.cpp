@@ -0,0 +1,12 @@
+#include <iostream>
+#include <string>
+
+/// Returns a greeting with C++ comment markers in a raw string.
+std::string greet() {
+    auto raw = R"(// raw string, "quotes" and /* too */)";
+    // The raw string above spans one line.
+    return raw; /* done */
+}
+
+/*! Entry point. */
+int main() { std::cout << greet() << '\n'; }
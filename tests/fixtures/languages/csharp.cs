@@ -0,0 +1,16 @@
+using System;
+
+/// <summary>Entry point.</summary>
+class Program
+{
+    static void Main()
+    {
+        var dir = @"C:\temp\"; // verbatim: the backslash does not escape
+        var json = """
+            { "url": "http://example.com/*" }
+            """;
+        /* block
+           comment */
+        Console.WriteLine(dir + json);
+    }
+}
@@ -0,0 +1,12 @@
+#include <cstdio>
+
+// Adds two vectors element by element.
+__global__ void add(const float *a, const float *b, float *c, int n) {
+    int i = blockIdx.x * blockDim.x + threadIdx.x;
+    if (i < n) c[i] = a[i] + b[i]; /* one thread per element */
+}
+
+int main() {
+    printf("launching // not a comment\n");
+    return 0;
+}
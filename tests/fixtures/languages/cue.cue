@@ -0,0 +1,12 @@
+// Service schema.
+#Service: {
+	name: string
+	port: int & >0 & <65536
+}
+
+service: #Service & {
+	name: """
+		// part of the string
+		"""
+	port: 8080
+}
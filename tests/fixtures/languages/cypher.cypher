@@ -0,0 +1,5 @@
+// Friends of friends.
+MATCH (me:Person {name: 'Ann'})-[:KNOWS]->()-[:KNOWS]->(fof)
+/* skip direct friends */
+WHERE NOT (me)-[:KNOWS]->(fof)
+RETURN fof.name AS name, '// not a comment' AS note;
@@ -0,0 +1,9 @@
+# cython: language_level=3
+cdef int square(int x):
+    '''Squares x.
+    # part of the docstring
+    '''
+    return x * x
+
+def py_square(x):
+    return square(x)  # trailing
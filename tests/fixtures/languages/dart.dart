@@ -0,0 +1,11 @@
+import 'dart:io';
+
+/// Prints a greeting.
+void main() {
+  /* outer /* nested */ comment */
+  final url = 'https://dart.dev/#/*';
+  final text = """
+    // part of the string
+  """;
+  stdout.writeln(url + text); // trailing
+}
@@ -0,0 +1,14 @@
+class
+	APPLICATION
+
+create
+	make
+
+feature
+	make
+			-- Print a greeting.
+		do
+			print ("Hello -- not a comment%N")
+		end
+
+end
@@ -0,0 +1,10 @@
+defmodule Greeter do
+  @moduledoc """
+  Greets people. A `#` here is part of the docstring.
+  """
+
+  # Returns a greeting for name.
+  def hello(name) do
+    "Hello, #{name}! # not a comment"
+  end
+end
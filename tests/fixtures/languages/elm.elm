@@ -0,0 +1,13 @@
+module Main exposing (main)
+
+import Html exposing (text)
+
+{-| Greets the user.
+-}
+greet : String -> String
+greet name =
+    -- triple-quoted strings span lines
+    """Hello, """ ++ name ++ " {- not a comment -}"
+
+main =
+    text (greet "world")
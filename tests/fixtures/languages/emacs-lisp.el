@@ -0,0 +1,7 @@
+;;; greet.el --- Greets the user  -*- lexical-binding: t -*-
+
+(defun greet (name)
+  "Return a greeting for NAME."
+  (format "Hello, %s ; not a comment" name))
+
+(provide 'greet) ; trailing
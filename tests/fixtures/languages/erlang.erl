@@ -0,0 +1,7 @@
+%%% Greeting module.
+-module(greet).
+-export([hello/1]).
+
+%% Returns a greeting.
+hello(Name) ->
+    "Hello, " ++ Name ++ " % not a comment". % trailing
@@ -0,0 +1,6 @@
+// A stereo sine.
+import("stdfaust.lib");
+
+/* frequency from a slider */
+freq = hslider("freq // not a comment", 440, 20, 2000, 1);
+process = os.osc(freq) <: _, _;
@@ -0,0 +1,6 @@
+# Greets the user.
+function greet --argument name
+    echo "Hello, $name # not a comment"
+end
+
+greet world # trailing
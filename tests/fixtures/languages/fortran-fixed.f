@@ -0,0 +1,15 @@
+C     Fixed-form Fortran: a C, c or * in column 1 starts a comment
+      PROGRAM HELLO
+      CHARACTER*20 MSG
+*     Code starts in column 7, so CALL never starts a line
+      MSG = 'HELLO ! NOT A COMMENT'
+      CALL GREET(MSG)
+      STOP
+      END
+
+      SUBROUTINE GREET(TEXT)
+      CHARACTER*(*) TEXT
+      PRINT *, TEXT
+c     lower-case c works too
+! and so does an exclamation mark
+      END
@@ -0,0 +1,12 @@
+! Free-form Fortran: comments start with "!"
+program hello
+  implicit none
+  character(len=*), parameter :: msg = "Hello ! not a comment"
+
+  call greet(msg)  ! trailing
+contains
+  subroutine greet(text)
+    character(len=*), intent(in) :: text
+    print *, text
+  end subroutine greet
+end program hello
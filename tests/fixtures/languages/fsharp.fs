@@ -0,0 +1,12 @@
+module Demo
+
+/// Greets the user.
+let greet name = sprintf "Hello, %s // not a comment" name
+
+(* outer
+   (* nested *)
+   still a comment *)
+let banner = """
+// part of the string
+"""
+printfn "%s%s" (greet "world") banner
@@ -0,0 +1,8 @@
+/// @description Moves the player.
+/// @param {real} spd Speed in pixels per step
+function move_player(spd) {
+    // horizontal input
+    var dir = keyboard_check(vk_right) - keyboard_check(vk_left);
+    x += dir * spd; /* trailing */
+    show_debug_message("moved // not a comment");
+}
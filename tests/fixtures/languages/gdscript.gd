@@ -0,0 +1,11 @@
+extends Node
+## A node that greets on ready.
+
+# The name to greet.
+@export var who: String = "world # not a comment"
+
+func _ready() -> void:
+	var text := """
+	# part of the string
+	"""
+	print("Hello, %s" % who, text)  # trailing
@@ -0,0 +1,9 @@
+# Greeting behavior.
+Feature: Greeting
+  Scenario: Greeting a user
+    Given a user named "Ann # not a comment"
+    When they open the app
+    Then they see:
+      """
+      Hello, Ann
+      """
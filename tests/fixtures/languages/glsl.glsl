@@ -0,0 +1,10 @@
+#version 330 core
+
+/* Fragment shader: a flat color */
+out vec4 FragColor;
+uniform vec3 color;
+
+void main() {
+    // full opacity
+    FragColor = vec4(color, 1.0);
+}
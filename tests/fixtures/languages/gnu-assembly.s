@@ -0,0 +1,11 @@
+/* x86-64 hello world (GNU as) */
+    .section .rodata
+msg:
+    .asciz "Hello"
+
+    .text
+    .globl main
+# main returns 0
+main:
+    xorl %eax, %eax   // trailing
+    ret
@@ -0,0 +1,4 @@
+# Plot a sine wave.
+set title "sin(x) # not a comment"
+set xrange [-pi:pi]
+plot sin(x) with lines  # trailing
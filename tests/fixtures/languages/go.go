@@ -0,0 +1,12 @@
+// Package greet shows that package comments count as documentation.
+package greet
+
+import "fmt"
+
+// Greet prints a greeting.
+func Greet(name string) {
+	msg := `raw string with // and /* */`
+	r := '"'
+	/* block comment */
+	fmt.Println(msg, name, r) // trailing
+}
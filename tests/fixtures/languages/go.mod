@@ -0,0 +1,9 @@
+module example.com/demo
+
+go 1.22
+
+// direct dependencies
+require (
+	golang.org/x/text v0.14.0
+	golang.org/x/sync v0.6.0 // indirect
+)
@@ -0,0 +1,12 @@
+/**
+ * Builds the greeting.
+ */
+def greet(name) {
+    // GString interpolation
+    def msg = "Hello, ${name} // not a comment"
+    def sql = '''
+        SELECT 1 /* inside a string */
+    '''
+    /* block comment */
+    msg + sql
+}
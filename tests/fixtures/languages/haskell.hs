@@ -0,0 +1,11 @@
+module Main where
+
+-- | Greets the user.
+greet :: String -> String
+greet name = "Hello, " ++ name ++ " -- not a comment"
+
+{- outer
+   {- nested -}
+   still a comment -}
+main :: IO ()
+main = putStrLn (greet "world") -- trailing
@@ -0,0 +1,10 @@
+/**
+ * Entry point.
+ */
+class Main {
+    static function main() {
+        // interpolation uses single quotes
+        var name = "world";
+        trace('Hello, $name // not a comment');
+    }
+}
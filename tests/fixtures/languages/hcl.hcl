@@ -0,0 +1,10 @@
+# Storage bucket for build artifacts.
+resource "aws_s3_bucket" "artifacts" {
+  bucket = "demo-artifacts # not a comment"
+
+  /* tags are
+     optional */
+  tags = {
+    team = "build" // trailing
+  }
+}
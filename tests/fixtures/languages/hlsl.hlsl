@@ -0,0 +1,8 @@
+// Pixel shader that samples a texture.
+Texture2D tex : register(t0);
+SamplerState samp : register(s0);
+
+/* Returns the sampled color */
+float4 main(float2 uv : TEXCOORD0) : SV_Target {
+    return tex.Sample(samp, uv); // trailing
+}
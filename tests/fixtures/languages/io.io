@@ -0,0 +1,6 @@
+// A greeter object.
+Greeter := Object clone do(
+  greet := method(name, "Hello, " .. name .. " // not a comment" println)
+)
+# hash comments work too
+Greeter greet("world")
@@ -0,0 +1,16 @@
+package demo;
+
+/**
+ * Greets people.
+ */
+public class Hello {
+    static final String TEXT = """
+        /* a text block, not a comment */
+        """;
+
+    // main entry
+    public static void main(String[] args) {
+        char c = '"';
+        System.out.println(TEXT + c); // print it
+    }
+}
@@ -0,0 +1,13 @@
+/**
+ * Builds a URL for the API client.
+ */
+export function url(path, params = {}) {
+  // Protocol-relative URLs keep their "//" prefix.
+  const base = "https://api.example.com"; // trailing comment
+  const query = `
+    /* not a comment: template literals span lines */
+    ${new URLSearchParams(params)}`;
+  /* a block comment
+     over two lines */
+  return base + path + "?" + query.trim();
+}
@@ -0,0 +1,7 @@
+<%@ page contentType="text/html" %>
+<%-- A greeting page --%>
+<html>
+<body>
+  <h1>Hello, <%= request.getParameter("name") %></h1>
+</body>
+</html>
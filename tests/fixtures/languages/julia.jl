@@ -0,0 +1,16 @@
+# Statistics helpers
+module Stats
+
+"""
+    mean(xs)
+
+Arithmetic mean; a `#` in a docstring is not a comment.
+"""
+mean(xs) = sum(xs) / length(xs)
+
+#= block comment
+   #= nested =#
+   still inside =#
+const HASH = '#'
+label = "total # of items" # trailing
+end
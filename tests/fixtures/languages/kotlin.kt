@@ -0,0 +1,13 @@
+package demo
+
+/**
+ * Greets [name].
+ */
+fun greet(name: String): String {
+    /* outer /* nested */ still a comment */
+    val template = """
+        // not a comment: $name
+    """
+    val quote = '"'
+    return template.trimIndent() + quote // trailing
+}
@@ -0,0 +1,8 @@
+/-- Doubles a natural number. -/
+def double (n : Nat) : Nat := 2 * n
+
+/- outer
+   /- nested -/
+   still a comment -/
+#eval double 21 -- trailing
+#eval "-- not a comment"
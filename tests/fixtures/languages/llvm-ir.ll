@@ -0,0 +1,10 @@
+; ModuleID = 'hello.c'
+@.str = private constant [14 x i8] c"Hello ; world\00"
+
+declare i32 @puts(ptr)
+
+; Entry point
+define i32 @main() {
+  %1 = call i32 @puts(ptr @.str) ; trailing
+  ret i32 0
+}
@@ -0,0 +1,14 @@
+-- Greeting module
+local M = {}
+
+--[[
+  A long comment.
+]]
+--[==[ level-2 comment with ]] inside ]==]
+function M.greet(name)
+  local s = "-- not a comment"
+  local long = [[
+-- inside a long string]]
+  return s .. long .. name
+end
+return M
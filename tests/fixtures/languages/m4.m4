@@ -0,0 +1,4 @@
+dnl Defines a greeting macro.
+define(`GREET', `Hello, $1!')dnl
+GREET(`world')
+# this comment is copied to the output
@@ -0,0 +1,10 @@
+# Build the demo binary.
+CC ?= cc
+CFLAGS := -O2 -Wall
+
+demo: main.o
+	$(CC) $(CFLAGS) -o $@ $^  # link
+
+.PHONY: clean
+clean:
+	rm -f demo *.o
@@ -0,0 +1,9 @@
+% Computes the mean of a vector.
+function m = vecmean(v)
+%{
+A block comment
+spanning lines.
+%}
+    m = sum(v) / numel(v);  % trailing
+    disp("mean % not a comment");
+end
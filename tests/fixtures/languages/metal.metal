@@ -0,0 +1,9 @@
+#include <metal_stdlib>
+using namespace metal;
+
+// Doubles each element.
+kernel void double_it(device float *data [[buffer(0)]],
+                      uint id [[thread_position_in_grid]]) {
+    /* one thread per element */
+    data[id] *= 2.0;
+}
@@ -0,0 +1,10 @@
+module 0x1::counter {
+    /// A counter resource.
+    struct Counter has key { value: u64 }
+
+    // Publishes a counter.
+    public fun publish(account: &signer) {
+        /* start at zero */
+        move_to(account, Counter { value: 0 });
+    }
+}
@@ -0,0 +1,11 @@
+## Greeting module.
+import strutils
+
+#[ A block comment
+   #[ nested ]#
+   still a comment ]#
+proc greet*(name: string): string =
+  ##[ Returns a greeting
+  for `name`. ]##
+  let raw = """# not a comment"""
+  result = "Hello, " & name & raw  # trailing
@@ -0,0 +1,11 @@
+# A development shell.
+{ pkgs ? import <nixpkgs> { } }:
+
+pkgs.mkShell {
+  packages = [ pkgs.cargo pkgs.rustc ];
+  shellHook = ''
+    # part of the string, not a Nix comment
+    echo "ready"
+  '';
+  /* a block comment */
+}
@@ -0,0 +1,13 @@
+#import <Foundation/Foundation.h>
+
+/** A greeter. */
+@interface Greeter : NSObject
+- (NSString *)greet;
+@end
+
+@implementation Greeter
+// Returns a fixed greeting.
+- (NSString *)greet {
+    return @"Hello /* not a comment */";
+}
+@end
@@ -0,0 +1,9 @@
+#import <Foundation/Foundation.h>
+#include <string>
+
+/* Mixes C++ strings and Objective-C objects. */
+NSString *greeting(const std::string &name) {
+    std::string text = "Hello, " + name + " // not a comment";
+    // convert to NSString
+    return [NSString stringWithUTF8String:text.c_str()];
+}
@@ -0,0 +1,7 @@
+(** Greets the user. *)
+let greet name = "Hello, " ^ name ^ " (* not a comment *)"
+
+(* outer
+   (* nested *)
+   still a comment *)
+let () = print_endline (greet "world")
@@ -0,0 +1,10 @@
+package main
+
+import "core:fmt"
+
+// Entry point.
+main :: proc() {
+	/* outer /* nested */ comment */
+	raw := `C:\path // not a comment`
+	fmt.println("Hellope! /*", raw) // trailing
+}
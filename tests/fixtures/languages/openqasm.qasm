@@ -0,0 +1,10 @@
+OPENQASM 3.0;
+include "stdgates.inc";
+
+// Bell state
+qubit[2] q;
+bit[2] c;
+/* entangle the qubits */
+h q[0];
+cx q[0], q[1];
+c = measure q;
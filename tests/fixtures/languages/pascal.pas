@@ -0,0 +1,12 @@
+{ Greeting program }
+program Hello;
+
+(* Multi-line
+   comment *)
+var
+  s: string;
+begin
+  s := 'Braces { } and // inside a string';
+  WriteLn(s); // trailing
+  { comment with (* inside *) }
+end.
@@ -0,0 +1,18 @@
+#!/usr/bin/perl
+use strict;
+use warnings;
+
+my $greeting = "Hello # not a comment";
+print <<"EOT";
+# heredoc text
+$greeting
+EOT
+
+=head1 NAME
+
+greet.pl - print a greeting
+
+=cut
+
+# done
+exit 0;
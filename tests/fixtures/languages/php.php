@@ -0,0 +1,13 @@
+<?php
+/**
+ * Renders a greeting.
+ */
+function greet(string $name): string
+{
+    # shell-style comment
+    // C++-style comment
+    $url = "http://example.com/#top"; // trailing
+    return <<<EOT
+    <p># Hello, $name</p>
+    EOT;
+}
@@ -0,0 +1,9 @@
+<#
+.SYNOPSIS
+    Greets the user.
+#>
+param([string]$Name = "world")
+
+# Build the message
+$message = "Hello, $Name # not a comment"
+Write-Output $message  # trailing
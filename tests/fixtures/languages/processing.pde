@@ -0,0 +1,13 @@
+/**
+ * Draws a circle that follows the mouse.
+ */
+void setup() {
+  size(400, 400);
+}
+
+void draw() {
+  // clear the frame
+  background(255);
+  ellipse(mouseX, mouseY, 20, 20);
+  text("/* not a comment */", 10, 10);
+}
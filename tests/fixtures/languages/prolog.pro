@@ -0,0 +1,9 @@
+% Family facts.
+parent(tom, bob).
+parent(bob, ann).
+
+/* grandparent/2 */
+grandparent(X, Z) :- parent(X, Y), parent(Y, Z).
+
+:- initialization(main).
+main :- format("~w~n", ['% not a comment']). % trailing
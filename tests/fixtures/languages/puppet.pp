@@ -0,0 +1,10 @@
+# Installs and runs nginx.
+class nginx {
+  package { 'nginx':
+    ensure => installed,
+  }
+  /* keep it running */
+  service { 'nginx':
+    ensure => running, # trailing
+  }
+}
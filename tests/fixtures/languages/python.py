@@ -0,0 +1,13 @@
+"""Read settings from simple `key = value` files.
+
+# This line is inside the docstring, so it is not a comment.
+"""
+import re
+
+# Matches a key, ignoring trailing "# comments" in the value.
+PATTERN = re.compile(r"^(\w+)\s*=\s*([^#]*)")
+
+
+def parse(text):
+    '''Return the settings in text as a dict.'''
+    return {m[1]: m[2].strip() for m in PATTERN.finditer(text)}  # one pass
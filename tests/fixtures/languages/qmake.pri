@@ -0,0 +1,6 @@
+# Shared settings.
+QT += core gui
+CONFIG += c++17
+
+SOURCES += main.cpp \
+           window.cpp
@@ -0,0 +1,12 @@
+import QtQuick
+
+// A window with a label.
+Window {
+    width: 320; height: 240
+    visible: true
+    /* centered text */
+    Text {
+        anchors.centerIn: parent
+        text: "Hello // not a comment"
+    }
+}
@@ -0,0 +1,8 @@
+#' Greets the user.
+#' @param name Who to greet.
+greet <- function(name = "world") {
+  # paste0 concatenates without separators
+  paste0("Hello, ", name, " # not a comment")
+}
+
+print(greet()) # trailing
@@ -0,0 +1,7 @@
+%%{
+  machine number;
+  # digits with an optional sign
+  main := [+\-]? digit+ ;
+}%%
+
+%% write data;
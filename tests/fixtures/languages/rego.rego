@@ -0,0 +1,10 @@
+package authz
+
+# Admins may do anything.
+default allow := false
+
+allow if {
+	input.user.role == "admin" # trailing
+}
+
+message := `# part of a raw string`
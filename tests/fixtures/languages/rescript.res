@@ -0,0 +1,5 @@
+/** Greets the user. */
+let greet = name => `Hello, ${name} // not a comment`
+
+// Print it
+Console.log(greet("world"))
@@ -0,0 +1,9 @@
+app [main] { pf: platform "https://example.com/platform.tar.br" }
+
+import pf.Stdout
+
+## Greets the user.
+greet = \name -> "Hello, $(name) # not a comment"
+
+# print it
+main = Stdout.line! (greet "world")
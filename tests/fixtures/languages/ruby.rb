@@ -0,0 +1,19 @@
+# frozen_string_literal: true
+
+require "json"
+
+=begin
+Block comments start with =begin in column 1
+  =end only counts in column 1 as well
+=end
+class Greeter
+  GREETING = "Hello # not a comment"
+
+  def query
+    <<~SQL
+      -- heredoc body
+      # still the heredoc
+      SELECT 1
+    SQL
+  end
+end
@@ -0,0 +1,12 @@
+//! Line scanner used by the fixtures.
+
+/// Returns where the first comment marker of `line` starts.
+pub fn marker(line: &str) -> Option<usize> {
+    // Markers inside literals do not count:
+    let slashes = "// not a comment";
+    let raw = r#"/* still a "string" */"#;
+    let quote = '"'; // a char literal, then a comment
+    /* outer /* nested */ still
+       inside the outer comment */
+    line.find(slashes).or_else(|| line.find(raw)).filter(|_| quote == '"')
+}
@@ -0,0 +1,10 @@
+object Main {
+  /** Entry point. */
+  def main(args: Array[String]): Unit = {
+    // greet everyone
+    val s = """raw "string" with // slashes"""
+    /* a /* nested */ comment */
+    val c = '"'
+    println(s + c)
+  }
+}
@@ -0,0 +1,7 @@
+;; Greets the user.
+(define (greet name)
+  (string-append "Hello, " name " ; not a comment"))
+
+#| outer #| nested |# comment |#
+(display (greet "world"))
+(newline)
@@ -0,0 +1,14 @@
+#!/bin/sh
+# Print a greeting.
+set -eu
+
+name="${1:-world}" # default name
+echo "# is not a comment inside quotes"
+echo 'neither # here'
+cat <<EOF
+# heredoc text, not a comment
+Hello, $name
+EOF
+cat <<-'END'
+	# indented heredoc with a quoted terminator
+	END
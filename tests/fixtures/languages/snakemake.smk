@@ -0,0 +1,9 @@
+# Count the lines of every sample.
+rule count:
+    input: "data/{sample}.txt"
+    output: "counts/{sample}.txt"
+    shell:
+        """
+        # part of the shell command string
+        wc -l {input} > {output}
+        """
@@ -0,0 +1,15 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.20;
+
+/// @title A simple counter
+contract Counter {
+    uint256 public count;
+    string public note = "// not a comment";
+
+    /**
+     * @notice Increments the counter.
+     */
+    function increment() external {
+        count += 1; /* trailing */
+    }
+}
@@ -0,0 +1,8 @@
+-- Active users by signup month.
+SELECT date_trunc('month', created_at) AS month,
+       count(*) AS users
+FROM users
+/* only active accounts */
+WHERE status = 'active -- not a comment'
+GROUP BY 1
+ORDER BY 1;
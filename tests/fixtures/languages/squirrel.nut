@@ -0,0 +1,12 @@
+// A counter class.
+class Counter {
+    count = 0;
+
+    # hash comments work too
+    function increment() {
+        count++;
+        return count; /* trailing */
+    }
+}
+
+print("count: " + Counter().increment() + " // not a comment\n");
@@ -0,0 +1,12 @@
+"""Macros for the demo package."""
+
+load("@rules_cc//cc:defs.bzl", "cc_library")
+
+# Defines a library with default copts.
+def demo_library(name, srcs, **kwargs):
+    cc_library(
+        name = name,
+        srcs = srcs,
+        copts = ["-DCOMMENT='#'"],  # escaped hash
+        **kwargs
+    )
@@ -0,0 +1,12 @@
+//! A counter contract.
+contract;
+
+abi Counter {
+    /// Returns the count.
+    fn count() -> u64;
+}
+
+// Always zero for now.
+impl Counter for Contract {
+    fn count() -> u64 { 0 } /* trailing */
+}
@@ -0,0 +1,12 @@
+import Foundation
+
+/// Returns a greeting.
+func greet(_ name: String) -> String {
+    /* outer
+       /* nested */
+       still a comment */
+    let text = """
+        /* not a comment */ Hello, \(name)
+        """
+    return text // trailing
+}
@@ -0,0 +1,10 @@
+// A parameterized register.
+module register #(parameter int W = 8) (
+  input  logic         clk,
+  input  logic [W-1:0] d,
+  output logic [W-1:0] q
+);
+  /* flop on the rising edge */
+  always_ff @(posedge clk) q <= d;
+  initial $display("W = %0d // not a comment", W);
+endmodule
@@ -0,0 +1,6 @@
+# Prints a greeting.
+proc greet {name} {
+    puts "Hello, $name # not a comment"
+}
+
+greet world ;# trailing
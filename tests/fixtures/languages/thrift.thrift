@@ -0,0 +1,12 @@
+namespace py demo
+
+/* A user record. */
+struct User {
+  1: i64 id,
+  2: string name = "# not a comment", // trailing
+}
+
+# services work too
+service Users {
+  User get(1: i64 id)
+}
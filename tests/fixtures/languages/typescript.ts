@@ -0,0 +1,17 @@
+import { readFile } from "node:fs/promises";
+
+/** A parsed `.env` entry. */
+export interface Entry {
+  key: string;
+  value: string; // without quotes
+}
+
+// Lines like `KEY="a # b"` keep their hash.
+export async function load(path: string): Promise<Entry[]> {
+  const text = await readFile(path, "utf8");
+  /* split on the first '=' */
+  return text.split("\n").filter(Boolean).map((line) => {
+    const [key, ...rest] = line.split("=");
+    return { key, value: rest.join("=").replace(/^"|"$/g, "") };
+  });
+}
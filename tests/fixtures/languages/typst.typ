@@ -0,0 +1,7 @@
+// Document settings.
+#set page(width: 10cm)
+
+= Introduction
+/* A block
+   comment */
+Visit #link("https://typst.app")[the website].
@@ -0,0 +1,8 @@
+import os
+
+// Prints a greeting.
+fn main() {
+	/* outer /* nested */ comment */
+	name := os.args[1] or { 'world' }
+	println('Hello, ${name} // not a comment')
+}
@@ -0,0 +1,15 @@
+/**
+ * A greeter.
+ */
+public class Greeter : Object {
+    // Returns a greeting.
+    public string greet (string name) {
+        return "Hello, %s /* not a comment */".printf (name);
+    }
+}
+
+/* Entry point */
+int main () {
+    stdout.printf ("%s\n", new Greeter ().greet ("world"));
+    return 0;
+}
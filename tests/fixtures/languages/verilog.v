@@ -0,0 +1,12 @@
+// A simple counter.
+module counter (
+    input  wire       clk,
+    input  wire       rst,
+    output reg  [7:0] count
+);
+  /* count up on every rising edge */
+  always @(posedge clk) begin
+    if (rst) count <= 8'd0;
+    else     count <= count + 8'd1; // trailing
+  end
+endmodule
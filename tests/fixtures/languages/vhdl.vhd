@@ -0,0 +1,12 @@
+library ieee;
+use ieee.std_logic_1164.all;
+
+-- A two-input AND gate.
+entity and_gate is
+  port (a, b : in std_logic; y : out std_logic);
+end entity;
+
+architecture rtl of and_gate is
+begin
+  y <= a and b; -- trailing
+end architecture;
@@ -0,0 +1,8 @@
+" Trim trailing whitespace on save.
+function! s:Trim() abort
+  let l:view = winsaveview()
+  keeppatterns %s/\s\+$//e
+  call winrestview(l:view)
+endfunction
+
+autocmd BufWritePre * call s:Trim()
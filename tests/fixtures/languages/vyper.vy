@@ -0,0 +1,11 @@
+# @version ^0.3.10
+
+owner: public(address)
+
+@external
+def __init__():
+    """
+    @notice Sets the owner.
+    # part of the docstring, not a comment
+    """
+    self.owner = msg.sender  # trailing
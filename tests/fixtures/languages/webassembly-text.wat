@@ -0,0 +1,9 @@
+(module
+  ;; Adds two numbers.
+  (func $add (param $a i32) (param $b i32) (result i32)
+    local.get $a
+    local.get $b
+    i32.add)
+  (; a block comment (; nested ;) still
+     inside ;)
+  (export "add ;; not a comment" (func $add)))
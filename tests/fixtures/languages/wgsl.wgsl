@@ -0,0 +1,7 @@
+// Vertex shader
+@vertex
+fn main(@builtin(vertex_index) i: u32) -> @builtin(position) vec4<f32> {
+  /* outer /* nested */ comment */
+  let x = f32(i) - 1.0;
+  return vec4<f32>(x, 0.0, 0.0, 1.0); // trailing
+}
@@ -0,0 +1,4 @@
+(* Squares of the first ten integers *)
+squares = Table[n^2, {n, 1, 10}];
+(* outer (* nested *) comment *)
+Print["Total (* not a comment *): ", Total[squares]]
@@ -0,0 +1,12 @@
+%{
+#include <stdio.h>
+%}
+
+%token NUMBER
+
+%%
+/* A sum of numbers */
+expr: expr '+' NUMBER { $$ = $1 + $3; }
+    | NUMBER             { printf("/* %d */\n", $1); }
+    ;
+%%
@@ -0,0 +1,11 @@
+//! Demo module.
+const std = @import("std");
+
+/// Entry point.
+pub fn main() void {
+    // multiline strings start each line with two backslashes
+    const text =
+        \\// part of the string, not a comment
+    ;
+    std.debug.print("{s} // {c}\n", .{ text, '"' });
+}
@@ -0,0 +1,57 @@
+use std::fs;
+use std::path::Path;
+
+fn run_json(root: &Path, extra: &[&str]) -> serde_json::Value {
+    let out = std::process::Command::new(env!("CARGO_BIN_EXE_ocloc"))
+        .arg(root)
+        .arg("--json")
+        .args(extra)
+        .output()
+        .expect("run ocloc");
+    assert!(out.status.success());
+    serde_json::from_slice(&out.stdout).expect("valid json")
+}
+
+#[test]
+fn generated_files_are_reported_separately() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    fs::write(root.join("main.rs"), "fn main() {}\n").unwrap();
+    fs::write(
+        root.join("api.rs"),
+        "// Code generated by tool. DO NOT EDIT.\npub struct A;\npub struct B;\n",
+    )
+    .unwrap();
+    let bundle = "var a=1;function b(c){return c*2+a}".repeat(100);
+    fs::write(root.join("app.min.js"), format!("{bundle}\n")).unwrap();
+
+    let v = run_json(root, &[]);
+    assert_eq!(v["languages"]["Rust"]["files"], 1);
+    assert!(v["languages"].get("JavaScript").is_none());
+    assert_eq!(v["totals"]["files"], 1);
+    assert_eq!(v["generated"]["Rust"]["code"], 2);
+    assert_eq!(v["generated"]["JavaScript"]["files"], 1);
+    assert_eq!(v["stats"]["generated_files"], 2);
+
+    let v = run_json(root, &["--include-generated"]);
+    assert_eq!(v["languages"]["Rust"]["files"], 2);
+    assert_eq!(v["languages"]["JavaScript"]["files"], 1);
+    assert!(v.get("generated").is_none());
+    assert_eq!(v["stats"]["generated_files"], 2);
+}
+
+#[test]
+fn marker_words_in_code_and_ordinary_comments_are_counted() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    fs::write(root.join("ids.py"), "AUTOGENERATED_IDS = [1, 2]\n").unwrap();
+    fs::write(
+        root.join("lib.rs"),
+        "// Please do not edit this without review\nfn a() {}\n",
+    )
+    .unwrap();
+
+    let v = run_json(root, &[]);
+    assert_eq!(v["totals"]["files"], 2);
+    assert!(v.get("generated").is_none());
+}
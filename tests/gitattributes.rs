@@ -0,0 +1,55 @@
+use std::fs;
+use std::path::Path;
+
+fn run_json(root: &Path, extra: &[&str]) -> serde_json::Value {
+    let out = std::process::Command::new(env!("CARGO_BIN_EXE_ocloc"))
+        .arg(root)
+        .arg("--json")
+        .args(extra)
+        .output()
+        .expect("run ocloc");
+    assert!(out.status.success());
+    serde_json::from_slice(&out.stdout).expect("valid json")
+}
+
+#[test]
+fn linguist_attributes_override_and_exclude() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    assert!(
+        std::process::Command::new("git")
+            .arg("init")
+            .current_dir(root)
+            .status()
+            .expect("git init")
+            .success()
+    );
+    fs::write(
+        root.join(".gitattributes"),
+        "gen/*.rs linguist-generated\nvendor/** linguist-vendored\n*.tpl linguist-language=Python\n",
+    )
+    .unwrap();
+    fs::create_dir_all(root.join("gen")).unwrap();
+    fs::create_dir_all(root.join("vendor/lib")).unwrap();
+    fs::write(root.join("main.rs"), "fn main() {}\n").unwrap();
+    fs::write(root.join("gen/api.rs"), "pub struct A;\npub struct B;\n").unwrap();
+    fs::write(root.join("vendor/lib/x.js"), "var x = 1;\n").unwrap();
+    fs::write(root.join("script.tpl"), "# comment\nprint(1)\n").unwrap();
+
+    let v = run_json(root, &[]);
+    let langs = &v["languages"];
+    assert_eq!(langs["Rust"]["files"], 1);
+    assert_eq!(langs["Rust"]["code"], 1);
+    assert!(langs.get("JavaScript").is_none());
+    assert_eq!(langs["Python"]["comment"], 1);
+    assert_eq!(langs["Python"]["code"], 1);
+    assert_eq!(v["stats"]["generated_files"], 1);
+    assert_eq!(v["stats"]["vendored_files"], 1);
+
+    let v = run_json(root, &["--include-generated"]);
+    let langs = &v["languages"];
+    assert_eq!(langs["Rust"]["files"], 2);
+    assert_eq!(langs["JavaScript"]["files"], 1);
+    assert_eq!(v["stats"]["generated_files"], 1);
+    assert_eq!(v["stats"]["vendored_files"], 1);
+}
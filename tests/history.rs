@@ -0,0 +1,91 @@
+use std::fs;
+use std::path::Path;
+
+mod common;
+
+use common::{git, ocloc};
+
+fn history(root: &Path, extra: &[&str]) -> String {
+    String::from_utf8(ocloc(root, &[&["history", "."], extra].concat())).unwrap()
+}
+
+#[test]
+fn history_reports_counts_per_sampled_commit() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    git(root, &["-c", "init.defaultBranch=main", "init", "-q"]);
+    for i in 1..=4 {
+        fs::write(root.join("lib.rs"), "fn f() {}\n".repeat(i)).unwrap();
+        if i == 3 {
+            fs::write(root.join("tool.py"), "# helper\nprint(1)\n").unwrap();
+        }
+        git(root, &["add", "."]);
+        git(root, &["commit", "-q", "-m", &format!("c{i}")]);
+    }
+
+    let v: serde_json::Value = serde_json::from_str(&history(root, &["--json"])).unwrap();
+    let points = v.as_array().unwrap();
+    let code: Vec<u64> = points
+        .iter()
+        .map(|p| p["totals"]["code"].as_u64().unwrap())
+        .collect();
+    assert_eq!(code, [1, 2, 4, 5]);
+    assert_eq!(points[2]["languages"]["Python"]["comment"], 1);
+    assert!(points[1]["languages"].get("Python").is_none());
+    assert!(points[0]["date"].as_str().unwrap().ends_with('Z'));
+
+    // Every second commit from the oldest, plus the newest
+    let v: serde_json::Value =
+        serde_json::from_str(&history(root, &["--json", "--every", "2"])).unwrap();
+    let code: Vec<u64> = v
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|p| p["totals"]["code"].as_u64().unwrap())
+        .collect();
+    assert_eq!(code, [1, 4, 5]);
+
+    let csv = history(root, &["--csv", "--from", "HEAD~1", "--ext", "rs"]);
+    let mut lines = csv.lines();
+    assert_eq!(
+        lines.next(),
+        Some("commit,date,language,files,code,comment,blank,total,doc")
+    );
+    let rows: Vec<&str> = lines.collect();
+    assert_eq!(rows.len(), 4);
+    assert!(rows[0].contains(",Rust,1,3,0,0,3,0"));
+    assert!(rows[3].contains(",Total,1,4,0,0,4,0"));
+}
+
+#[test]
+fn history_applies_each_commits_gitattributes() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    git(root, &["-c", "init.defaultBranch=main", "init", "-q"]);
+    fs::create_dir_all(root.join("vendor")).unwrap();
+    fs::write(root.join("lib.rs"), "fn f() {}\n").unwrap();
+    fs::write(root.join("vendor/dep.rs"), "fn d() {}\nfn e() {}\n").unwrap();
+    fs::write(root.join("schema.rs"), "fn s() {}\n".repeat(3)).unwrap();
+    git(root, &["add", "."]);
+    git(root, &["commit", "-q", "-m", "c1"]);
+    fs::write(
+        root.join(".gitattributes"),
+        "vendor/** linguist-vendored\nschema.rs linguist-generated\n",
+    )
+    .unwrap();
+    git(root, &["add", "."]);
+    git(root, &["commit", "-q", "-m", "c2"]);
+
+    let v: serde_json::Value = serde_json::from_str(&history(root, &["--json"])).unwrap();
+    let code: Vec<u64> = v
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|p| p["languages"]["Rust"]["code"].as_u64().unwrap())
+        .collect();
+    assert_eq!(code, [6, 1]);
+
+    let v: serde_json::Value =
+        serde_json::from_str(&history(root, &["--json", "--include-generated"])).unwrap();
+    assert_eq!(v[1]["languages"]["Rust"]["code"], 6);
+}
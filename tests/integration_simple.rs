@@ -22,6 +22,7 @@ fn runs_on_simple_fixture() {
 }
 
 #[test]
+#[allow(deprecated)]
 fn analyze_reader_parity_with_analyze_file() {
     use std::io::Cursor;
     let dir = tempfile::tempdir().unwrap();
@@ -36,3 +37,22 @@ fn analyze_reader_parity_with_analyze_file() {
     assert_eq!(file_counts.comment, reader_counts.comment);
     assert_eq!(file_counts.blank, reader_counts.blank);
 }
+
+#[test]
+fn analyzer_reader_parity_with_analyze_file() {
+    use std::io::Cursor;
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("parity.rs");
+    std::fs::write(&path, "// c1\nfn main() {}\n/* x */\n").unwrap();
+
+    let analyzer = ocloc::Analyzer::default();
+    let file_counts = analyzer.analyze_file(&path).unwrap();
+    let data = std::fs::read(&path).unwrap();
+    let reader_counts = analyzer
+        .analyze_reader(&mut Cursor::new(data), &path)
+        .unwrap();
+    assert_eq!(file_counts.total, reader_counts.total);
+    assert_eq!(file_counts.code, reader_counts.code);
+    assert_eq!(file_counts.comment, reader_counts.comment);
+    assert_eq!(file_counts.blank, reader_counts.blank);
+}
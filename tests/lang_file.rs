@@ -0,0 +1,77 @@
+use std::process::Command;
+
+fn ocloc(config_home: &std::path::Path) -> Command {
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_ocloc"));
+    cmd.env("XDG_CONFIG_HOME", config_home);
+    cmd
+}
+
+#[test]
+fn lang_file_and_user_config_add_languages() {
+    let dir = tempfile::tempdir().unwrap();
+    let src = dir.path().join("src");
+    std::fs::create_dir(&src).unwrap();
+    std::fs::write(
+        src.join("build.pipeline"),
+        "-- stage\nrun build\n\nrun test\n",
+    )
+    .unwrap();
+    std::fs::write(src.join("page.tmpl"), "{# note #}\n<p>{{ x }}</p>\n").unwrap();
+    std::fs::write(src.join("util.h"), "# not C here\nx\n").unwrap();
+
+    // Per-user config defines .pipeline
+    let config = dir.path().join("config");
+    std::fs::create_dir_all(config.join("ocloc")).unwrap();
+    std::fs::write(
+        config.join("ocloc/languages.json"),
+        r#"[{"name": "Pipeline", "extensions": ["pipeline"], "line_markers": ["--"]}]"#,
+    )
+    .unwrap();
+    // --lang-file adds .tmpl and claims .h from C
+    let lang_file = dir.path().join("extra.json");
+    std::fs::write(
+        &lang_file,
+        r##"[
+  {"name": "Tmpl", "extensions": ["tmpl"], "line_markers": [], "block_markers": ["{#", "#}"]},
+  {"name": "Hash", "extensions": ["h"], "line_markers": ["#"]}
+]"##,
+    )
+    .unwrap();
+
+    let out = ocloc(&config)
+        .arg(&src)
+        .arg("--json")
+        .arg("--lang-file")
+        .arg(&lang_file)
+        .output()
+        .expect("run ocloc");
+    assert!(out.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&out.stdout).unwrap();
+    let langs = &json["languages"];
+    assert_eq!(langs["Pipeline"]["code"], 2);
+    assert_eq!(langs["Pipeline"]["comment"], 1);
+    assert_eq!(langs["Tmpl"]["comment"], 1);
+    assert_eq!(langs["Hash"]["comment"], 1);
+    assert!(langs.get("C").is_none());
+
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(
+        stderr.contains(".h is claimed by both C and Hash"),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn invalid_lang_file_is_an_error() {
+    let dir = tempfile::tempdir().unwrap();
+    let bad = dir.path().join("bad.json");
+    std::fs::write(&bad, "{ not json").unwrap();
+    let out = ocloc(dir.path())
+        .arg(dir.path())
+        .arg("--lang-file")
+        .arg(&bad)
+        .output()
+        .expect("run ocloc");
+    assert!(!out.status.success());
+    assert!(String::from_utf8_lossy(&out.stderr).contains("parse language file"));
+}
@@ -0,0 +1,72 @@
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::PathBuf;
+
+use ocloc::Analyzer;
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+struct Expected {
+    file: String,
+    language: String,
+    code: usize,
+    comment: usize,
+    blank: usize,
+    doc: usize,
+}
+
+fn fixtures_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/languages")
+}
+
+fn load_expected() -> Vec<Expected> {
+    let manifest = std::fs::read_to_string(fixtures_dir().join("expected.json")).unwrap();
+    serde_json::from_str(&manifest).unwrap()
+}
+
+#[test]
+fn every_fixture_is_detected_and_counted() {
+    let analyzer = Analyzer::default();
+    let mut failures = Vec::new();
+    for e in load_expected() {
+        let path = fixtures_dir().join(&e.file);
+        let detected = analyzer.registry().find_for_path(&path);
+        if detected != Some(e.language.as_str()) {
+            failures.push(format!(
+                "{}: detected as {:?}, expected {}",
+                e.file, detected, e.language
+            ));
+            continue;
+        }
+        let mut reader = BufReader::new(File::open(&path).unwrap());
+        let c = analyzer.analyze_reader(&mut reader, &path).unwrap();
+        let got = (c.code, c.comment, c.blank, c.doc);
+        let want = (e.code, e.comment, e.blank, e.doc);
+        if got != want {
+            failures.push(format!(
+                "{} ({}): got (code, comment, blank, doc) = {:?}, expected {:?}",
+                e.file, e.language, got, want
+            ));
+        }
+    }
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
+
+#[test]
+fn every_builtin_language_has_a_fixture() {
+    let covered: HashSet<String> = load_expected().into_iter().map(|e| e.language).collect();
+    let analyzer = Analyzer::default();
+    let missing: Vec<&str> = analyzer
+        .registry()
+        .specs()
+        .iter()
+        .map(|s| s.name.as_str())
+        .filter(|name| !covered.contains(*name))
+        .collect();
+    assert!(
+        missing.is_empty(),
+        "languages without a fixture: {:?}",
+        missing
+    );
+}
@@ -0,0 +1,108 @@
+use std::fs;
+use std::path::Path;
+
+mod common;
+
+use common::{git, ocloc_json};
+
+fn run_json(path: &Path, extra: &[&str]) -> serde_json::Value {
+    ocloc_json(path, &[&[".", "--json"], extra].concat())
+}
+
+#[test]
+fn rev_counts_a_commit_without_checking_it_out() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().join("work");
+    fs::create_dir_all(root.join("src")).unwrap();
+    git(&root, &["-c", "init.defaultBranch=main", "init"]);
+    fs::write(root.join("src/main.rs"), "// entry\nfn main() {}\n").unwrap();
+    fs::write(root.join("tool.py"), "print(1)\n").unwrap();
+    git(&root, &["add", "."]);
+    git(&root, &["commit", "-q", "-m", "one"]);
+    git(&root, &["tag", "v1"]);
+    fs::write(root.join("src/main.rs"), "fn main() {}\nfn more() {}\n").unwrap();
+    fs::write(root.join("src/extra.rs"), "fn extra() {}\n").unwrap();
+    git(&root, &["add", "."]);
+    git(&root, &["commit", "-q", "-m", "two"]);
+    // Uncommitted changes are not seen by --rev
+    fs::write(root.join("scratch.rs"), "fn scratch() {}\n").unwrap();
+
+    let v = run_json(&root, &["--rev", "v1"]);
+    assert_eq!(v["languages"]["Rust"]["files"], 1);
+    assert_eq!(v["languages"]["Rust"]["comment"], 1);
+    assert_eq!(v["languages"]["Python"]["code"], 1);
+
+    let v = run_json(&root, &["--rev", "HEAD", "--by-file"]);
+    assert_eq!(v["languages"]["Rust"]["files"], 2);
+    assert_eq!(v["languages"]["Rust"]["code"], 3);
+    let sep = std::path::MAIN_SEPARATOR;
+    assert_eq!(v["by_file"][0]["path"], format!("src{sep}main.rs"));
+
+    // A subdirectory of the work tree limits the count to that part of the tree
+    let v = run_json(&root.join("src"), &["--rev", "v1"]);
+    assert!(v["languages"].get("Python").is_none());
+    assert_eq!(v["totals"]["files"], 1);
+
+    // Bare clones have no work tree at all
+    let bare = dir.path().join("mirror.git");
+    git(
+        dir.path(),
+        &[
+            "clone",
+            "-q",
+            "--bare",
+            root.to_str().unwrap(),
+            "mirror.git",
+        ],
+    );
+    let v = run_json(&bare, &["--rev", "v1", "--ext", "py"]);
+    assert_eq!(v["totals"]["files"], 1);
+    assert_eq!(v["languages"]["Python"]["code"], 1);
+}
+
+#[test]
+fn rev_applies_the_revisions_gitattributes_and_size_filters() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    fs::create_dir_all(root.join("vendor")).unwrap();
+    fs::create_dir_all(root.join("api")).unwrap();
+    git(root, &["-c", "init.defaultBranch=main", "init"]);
+    fs::write(
+        root.join(".gitattributes"),
+        "*.tpl linguist-language=Python\nvendor/** linguist-vendored\n",
+    )
+    .unwrap();
+    // Deeper files take precedence over the root one
+    fs::write(root.join("api/.gitattributes"), "*.rs linguist-generated\n").unwrap();
+    fs::write(root.join("main.rs"), "fn main() {}\n").unwrap();
+    fs::write(root.join("view.tpl"), "x = 1\ny = 2\n").unwrap();
+    fs::write(root.join("vendor/lib.rs"), "fn lib() {}\n").unwrap();
+    fs::write(root.join("api/types.rs"), "struct T;\n").unwrap();
+    fs::write(root.join("big.rs"), "fn big() {}\n".repeat(100)).unwrap();
+    git(root, &["add", "."]);
+    git(root, &["commit", "-q", "-m", "one"]);
+    // Work tree edits to .gitattributes do not change what --rev sees
+    fs::write(root.join(".gitattributes"), "*.rs linguist-vendored\n").unwrap();
+
+    let v = run_json(root, &["--rev", "HEAD", "--by-file"]);
+    assert_eq!(v["languages"]["Python"]["code"], 2);
+    assert_eq!(v["languages"]["Rust"]["files"], 2);
+    assert_eq!(v["generated"]["Rust"]["files"], 1);
+    assert_eq!(v["stats"]["vendored_files"], 1);
+    let tpl = v["by_file"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|f| f["path"] == "view.tpl")
+        .unwrap();
+    assert_eq!(tpl["detected_by"], "attribute");
+
+    let v = run_json(root, &["--rev", "HEAD", "--include-generated"]);
+    assert_eq!(v["languages"]["Rust"]["files"], 4);
+
+    let v = run_json(root, &["--rev", "HEAD", "--max-size", "100"]);
+    assert_eq!(v["languages"]["Rust"]["files"], 1);
+    let v = run_json(root, &["--rev", "HEAD", "--min-size", "100"]);
+    assert_eq!(v["languages"]["Rust"]["files"], 1);
+    assert_eq!(v["languages"]["Rust"]["code"], 100);
+}